@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::fs::{self, OpenOptions, Permissions};
+use std::io::{self, Write};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::source::SourceKind;
+
+// Service name for tokens saved in the OS keyring.
+const KEYRING_SERVICE: &str = "released";
+
+static TOKENS: Mutex<Option<HashMap<String, Option<String>>>> = Mutex::new(None);
+
+/// Where a token was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenSource {
+    /// The host's `token` in `config.toml`.
+    Config,
+    /// An environment variable, e.g. `GH_TOKEN`.
+    Environment(String),
+    /// Saved with `released auth login` in the OS keyring.
+    Keyring,
+    /// Saved with `released auth login` in a file, where there's no keyring.
+    File(PathBuf),
+    /// Logged in with the GitHub CLI.
+    GitHubCli,
+    /// A `machine` entry in `~/.netrc`.
+    Netrc(PathBuf),
+}
+
+impl Display for TokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Config => write!(f, "config.toml"),
+            Self::Environment(variable) => write!(f, "${variable}"),
+            Self::Keyring => write!(f, "keyring"),
+            Self::File(path) | Self::Netrc(path) => write!(f, "{}", path.display()),
+            Self::GitHubCli => write!(f, "gh CLI"),
+        }
+    }
+}
+
+/// A token for `host`, from the first of:
+///
+/// 1. The environment `variables`, in order.
+/// 2. A token saved with `released auth login`.
+/// 3. The GitHub CLI's login for the host.
+/// 4. `~/.netrc`.
+///
+/// Looked up once per host, a source is created for every package.
+pub fn token(host: &'_ str, variables: &'_ [&'_ str]) -> Option<String> {
+    TOKENS
+        .lock()
+        .expect("Token lock poisoned")
+        .get_or_insert_with(HashMap::new)
+        .entry(host.to_string())
+        .or_insert_with(|| find(host, variables).map(|(token, _)| token))
+        .clone()
+}
+
+/// Like `token`, also saying where it was found.
+pub fn find(host: &'_ str, variables: &'_ [&'_ str]) -> Option<(String, TokenSource)> {
+    let from_environment = variables.iter().find_map(|variable| {
+        std::env::var(variable)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .map(|token| (token, TokenSource::Environment((*variable).to_string())))
+    });
+
+    if let Some(found) = from_environment.or_else(|| stored(host)) {
+        return Some(found);
+    }
+
+    if let Some(token) = github_cli(host) {
+        return Some((token, TokenSource::GitHubCli));
+    }
+
+    let path = netrc_path()?;
+
+    netrc(&path, host).map(|token| (token, TokenSource::Netrc(path)))
+}
+
+/// Environment variables holding the token for `host`, following the forges' own CLIs.
+pub fn variables(kind: SourceKind, host: &'_ str) -> &'static [&'static str] {
+    match kind {
+        SourceKind::GitHub if host == "github.com" => &["GH_TOKEN", "GITHUB_TOKEN"],
+        SourceKind::GitHub => &["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"],
+        SourceKind::GitLab => &["GITLAB_TOKEN"],
+        SourceKind::Gitea => &["GITEA_TOKEN"],
+        SourceKind::Template => &[],
+    }
+}
+
+fn keyring_entry(host: &'_ str) -> Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, host).context("Unable to access the keyring")
+}
+
+fn credentials_path() -> Result<PathBuf> {
+    let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get config directory")?;
+
+    Ok(xdg_dir.get_config_home().join("credentials.toml"))
+}
+
+fn read_credentials() -> Result<BTreeMap<String, String>> {
+    let path = credentials_path()?;
+
+    match fs::read_to_string(&path) {
+        Ok(s) => toml::from_str(&s).context(format!("Unable to parse {path:?}")),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context(format!("Unable to read {path:?}")),
+    }
+}
+
+fn write_credentials(credentials: &'_ BTreeMap<String, String>) -> Result<PathBuf> {
+    let path = credentials_path()?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("Unable to create {parent:?}"))?;
+    }
+
+    // Only readable by the owner, the tokens are stored in plain text.
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .context(format!("Unable to write {path:?}"))?;
+
+    // The mode only applies when the file is created, an existing one may have been readable by others.
+    file.set_permissions(Permissions::from_mode(0o600))
+        .context(format!("Unable to restrict the permissions of {path:?}"))?;
+
+    file.write_all(toml::to_string(credentials)?.as_bytes())
+        .context(format!("Unable to write {path:?}"))?;
+
+    Ok(path)
+}
+
+/// A token saved with `released auth login`.
+fn stored(host: &'_ str) -> Option<(String, TokenSource)> {
+    match keyring_entry(host).and_then(|entry| Ok(entry.get_password()?)) {
+        Ok(token) => return Some((token, TokenSource::Keyring)),
+        Err(e) => debug!("No token for {host} in the keyring: {e}"),
+    }
+
+    let token = read_credentials().ok()?.remove(host)?;
+
+    Some((token, TokenSource::File(credentials_path().ok()?)))
+}
+
+/// Save `token` for `host` in the keyring, or in a file readable only by the user when there is no keyring.
+pub fn save(host: &'_ str, token: &'_ str) -> Result<TokenSource> {
+    match keyring_entry(host).and_then(|entry| Ok(entry.set_password(token)?)) {
+        Ok(()) => {
+            // Don't leave an older token behind in the file.
+            let mut credentials = read_credentials()?;
+
+            if credentials.remove(host).is_some() {
+                write_credentials(&credentials)?;
+            }
+
+            Ok(TokenSource::Keyring)
+        }
+        Err(e) => {
+            warn!("Unable to save the token in the keyring, saving it to a file instead: {e}");
+
+            let mut credentials = read_credentials()?;
+
+            credentials.insert(host.to_string(), token.to_string());
+
+            Ok(TokenSource::File(write_credentials(&credentials)?))
+        }
+    }
+}
+
+/// Remove the token saved for `host`, returning whether there was one.
+pub fn remove(host: &'_ str) -> Result<bool> {
+    let from_keyring = match keyring_entry(host).and_then(|entry| Ok(entry.delete_credential()?)) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("Nothing removed from the keyring for {host}: {e}");
+            false
+        }
+    };
+
+    let mut credentials = read_credentials()?;
+    let from_file = credentials.remove(host).is_some();
+
+    if from_file {
+        write_credentials(&credentials)?;
+    }
+
+    Ok(from_keyring || from_file)
+}
+
+#[derive(Deserialize)]
+struct GitHubCliHost {
+    user: Option<String>,
+    oauth_token: Option<String>,
+}
+
+/// The token the GitHub CLI (`gh auth login`) uses for `host`.
+fn github_cli(host: &'_ str) -> Option<String> {
+    let config_dir = std::env::var_os("GH_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| xdg::BaseDirectories::with_prefix("gh").ok().as_ref().map(xdg::BaseDirectories::get_config_home))?;
+
+    let hosts: BTreeMap<String, GitHubCliHost> = serde_yaml_ng::from_str(&fs::read_to_string(config_dir.join("hosts.yml")).ok()?).ok()?;
+    let settings = hosts.get(host)?;
+
+    // Newer versions of gh keep the token in the keyring, under the user's name.
+    settings.oauth_token.clone().or_else(|| {
+        let user = settings.user.as_deref()?;
+
+        keyring::Entry::new(&format!("gh:{host}"), user).ok()?.get_password().ok()
+    })
+}
+
+/// `~/.netrc`, or `$NETRC`, if it exists.
+fn netrc_path() -> Option<PathBuf> {
+    std::env::var_os("NETRC")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".netrc")))
+        .filter(|path| path.exists())
+}
+
+/// The password of the `machine` entry for `host`, or its API host, e.g. `api.github.com`.
+fn netrc(path: &'_ Path, host: &'_ str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    let api_host = format!("api.{host}");
+
+    let mut tokens = contents.split_whitespace();
+    let mut machine = None;
+    let mut found = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => machine = tokens.next(),
+            "default" => machine = Some("default"),
+            "password" => {
+                let password = tokens.next()?;
+
+                match machine {
+                    Some(m) if m == host || m == api_host => return Some(password.to_string()),
+                    Some("default") => found = found.or(Some(password.to_string())),
+                    _ => {}
+                }
+            }
+            // Skip the values of the other keywords.
+            "login" | "account" => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+
+    found
+}
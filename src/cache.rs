@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use crate::config::Signature;
+
+static OFFLINE: OnceLock<bool> = OnceLock::new();
+
+// Packages are downloaded concurrently, so updates to the index are serialized.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// A downloaded asset, stored under its SHA-256 digest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub url: String,
+    pub name: String,
+    pub sha256: String,
+    pub size: u64,
+    /// Seconds since the epoch when the asset was last installed from, or stored in, the cache.
+    pub last_used: u64,
+    /// The signature policy the asset was verified with before it was cached, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+}
+
+impl Entry {
+    pub fn last_used(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.last_used)
+    }
+}
+
+/// Install only from the cache, without touching the network.
+pub fn set_offline(offline: bool) {
+    OFFLINE.get_or_init(|| offline);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.get().copied().unwrap_or_default()
+}
+
+fn cache_home() -> Result<PathBuf> {
+    let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get cache directory")?;
+
+    Ok(xdg_dir.get_cache_home())
+}
+
+/// Where API responses are kept, to be revalidated with their `ETag`.
+pub fn responses_dir() -> Result<PathBuf> {
+    Ok(cache_home()?.join("api"))
+}
+
+fn blob_path(cache_home: &'_ Path, sha256: &'_ str) -> PathBuf {
+    cache_home.join("sha256").join(sha256)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).as_ref().map(Duration::as_secs).unwrap_or_default()
+}
+
+fn read_index(cache_home: &'_ Path) -> Result<BTreeMap<String, Entry>> {
+    match fs::read_to_string(cache_home.join("index.json")) {
+        Ok(s) => Ok(serde_json::from_str(&s).unwrap_or_else(|e| {
+            warn!("Ignoring the unreadable cache index: {e}");
+            BTreeMap::new()
+        })),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e).context("Unable to read the cache index"),
+    }
+}
+
+fn write_index(cache_home: &'_ Path, index: &'_ BTreeMap<String, Entry>) -> Result<()> {
+    fs::create_dir_all(cache_home).context(format!("Unable to create {cache_home:?}"))?;
+
+    let index = serde_json::to_string_pretty(index).context("Failed to serialize the cache index")?;
+
+    fs::write(cache_home.join("index.json"), index).context("Unable to write the cache index")
+}
+
+/// SHA-256 digest of the file at `path`.
+pub fn file_digest(path: &'_ Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+
+    io::copy(&mut File::open(path)?, &mut hasher).context(format!("Unable to read {path:?}"))?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Copy a cached asset downloaded from `url`, or with the `expected` digest, to `destination`.
+///
+/// Returns its entry, or `None` when the asset isn't cached.
+pub fn restore(url: &'_ Url, expected: Option<&'_ str>, destination: &'_ Path) -> Result<Option<Entry>> {
+    let _guard = INDEX_LOCK.lock().expect("Cache lock poisoned");
+
+    let cache_home = cache_home()?;
+    let mut index = read_index(&cache_home)?;
+
+    // The same asset may have been cached from another URL, e.g. a mirror.
+    let key = index
+        .get(url.as_str())
+        .filter(|entry| expected.is_none_or(|expected| entry.sha256 == expected))
+        .map(|_| url.to_string())
+        .or_else(|| expected.and_then(|expected| index.iter().find(|(_, entry)| entry.sha256 == expected).map(|(key, _)| key.clone())));
+
+    let Some(entry) = key.and_then(|key| index.get_mut(&key)) else {
+        return Ok(None);
+    };
+
+    let blob = blob_path(&cache_home, &entry.sha256);
+
+    if !blob.exists() {
+        return Ok(None);
+    }
+
+    debug!("Restoring {} from {blob:?}", entry.name);
+
+    fs::copy(&blob, destination).context(format!("Unable to copy {blob:?} to {destination:?}"))?;
+
+    let digest = file_digest(destination)?;
+
+    // Don't trust a cached file which has since been modified, it will be downloaded again.
+    if digest != entry.sha256 {
+        warn!("Cached {} is corrupt, removing it", entry.name);
+
+        let _ = fs::remove_file(&blob);
+
+        return Ok(None);
+    }
+
+    entry.last_used = now();
+
+    let entry = entry.clone();
+
+    write_index(&cache_home, &index)?;
+
+    Ok(Some(entry))
+}
+
+/// Keep a copy of the asset downloaded from `url` to `path`, along with the `signature` policy it was verified with.
+pub fn store(url: &'_ Url, path: &'_ Path, sha256: &'_ str, signature: Option<&'_ Signature>) -> Result<()> {
+    let _guard = INDEX_LOCK.lock().expect("Cache lock poisoned");
+
+    let cache_home = cache_home()?;
+    let blob = blob_path(&cache_home, sha256);
+
+    if !blob.exists() {
+        let parent = blob.parent().expect("Cached blobs have a parent directory");
+
+        fs::create_dir_all(parent).context(format!("Unable to create {parent:?}"))?;
+
+        // Copy then rename, so a blob is never partially written.
+        let temp = blob.with_extension(format!("{}.tmp", std::process::id()));
+
+        fs::copy(path, &temp).context(format!("Unable to copy {path:?} to the cache"))?;
+        fs::rename(&temp, &blob).context(format!("Unable to move {temp:?} to {blob:?}"))?;
+    }
+
+    let mut index = read_index(&cache_home)?;
+
+    // Don't forget an earlier verification of the same file, when this install had no signature policy.
+    let signature = signature
+        .cloned()
+        .or_else(|| index.values().filter(|entry| entry.sha256 == sha256).find_map(|entry| entry.signature.clone()));
+
+    index.insert(
+        url.to_string(),
+        Entry {
+            url: url.to_string(),
+            name: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            sha256: sha256.to_string(),
+            size: fs::metadata(&blob).as_ref().map(fs::Metadata::len).unwrap_or_default(),
+            last_used: now(),
+            signature,
+        },
+    );
+
+    write_index(&cache_home, &index)
+}
+
+/// Everything in the cache, most recently used first.
+pub fn entries() -> Result<Vec<Entry>> {
+    let cache_home = cache_home()?;
+
+    let mut entries: Vec<Entry> = read_index(&cache_home)?
+        .into_values()
+        .filter(|entry| blob_path(&cache_home, &entry.sha256).exists())
+        .collect();
+
+    entries.sort_by(|a, b| b.last_used.cmp(&a.last_used).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(entries)
+}
+
+/// Remove cached assets for which `matches` is true, returning them.
+pub fn remove(matches: impl Fn(&Entry) -> bool) -> Result<Vec<Entry>> {
+    let _guard = INDEX_LOCK.lock().expect("Cache lock poisoned");
+
+    let cache_home = cache_home()?;
+
+    let (removed, kept): (BTreeMap<String, Entry>, BTreeMap<String, Entry>) = read_index(&cache_home)?.into_iter().partition(|(_, entry)| matches(entry));
+
+    for entry in removed.values() {
+        // The same file may be published under several URLs.
+        if kept.values().any(|kept| kept.sha256 == entry.sha256) {
+            continue;
+        }
+
+        let blob = blob_path(&cache_home, &entry.sha256);
+
+        debug!("Removing {blob:?}");
+
+        if let Err(e) = fs::remove_file(&blob) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(e).context(format!("Unable to remove {blob:?}"));
+            }
+        }
+    }
+
+    write_index(&cache_home, &kept)?;
+
+    Ok(removed.into_values().collect())
+}
+
+/// Remove cached API responses last used before `before`, or all of them, returning how many and their total size.
+pub fn remove_responses(before: Option<SystemTime>) -> Result<(usize, u64)> {
+    let dir = responses_dir()?;
+
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e).context(format!("Unable to read {dir:?}")),
+    };
+
+    let (mut count, mut size) = (0, 0);
+
+    for entry in entries.filter_map(std::result::Result::ok) {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        // Responses are rewritten whenever they're revalidated, so the modification time is when they were last used.
+        if before.is_some_and(|before| metadata.modified().is_ok_and(|modified| modified >= before)) {
+            continue;
+        }
+
+        debug!("Removing {:?}", entry.path());
+
+        fs::remove_file(entry.path()).context(format!("Unable to remove {:?}", entry.path()))?;
+
+        count += 1;
+        size += metadata.len();
+    }
+
+    Ok((count, size))
+}
@@ -1,4 +1,5 @@
 use std::io::Cursor;
+use std::path::PathBuf;
 
 use clap::Args;
 use git_url_parse::GitUrl;
@@ -10,12 +11,13 @@ use tracing::{error, info};
 
 use crate::{
     cli::{Result, RunCommand},
-    config::{Config, Package},
+    config::{Binary, Config, Package},
     errors::CommandError,
     install,
+    source::{ReleaseSource, Source, SourceKind},
     spinner::spinner,
-    system::System,
-    version,
+    system::{System, Target},
+    version::{self, Version},
 };
 
 #[derive(Debug, Clone, Args)]
@@ -23,6 +25,8 @@ pub struct Add {
     /// Name of the package to install.
     ///
     /// To install a specific version use name@version, for example: `cli/cli@v2.4.0`
+    ///
+    /// A SemVer requirement such as `cli/cli@^2` is saved and honored by `update`.
     name: String,
     /// Alias to use instead of the repository name.
     ///
@@ -37,11 +41,16 @@ pub struct Add {
     /// Filter used to find the executable.
     #[arg(short, long)]
     file_filter: Option<String>,
+    /// Binary to install from the release archive, may be given multiple times.
+    ///
+    /// Use `name=rename` to install the binary under a different name.
+    #[arg(short, long = "bin", value_name = "NAME[=RENAME]")]
+    binaries: Vec<Binary>,
     /// Allow install of pre-release versions of the package.
     ///
-    /// When `show` is provided this includes pre-release versions in the list,
-    /// When it is not the most recent version is selected, that could be a pre-release or
-    /// official release depending on release date.
+    /// When `show` is provided this includes pre-release versions in the list.
+    /// When it is not, the newest release is selected whether or not it is a pre-release,
+    /// and `update` keeps following pre-releases.
     #[arg(short = 'P', long)]
     pre_release: bool,
     /// Show available versions
@@ -49,6 +58,31 @@ pub struct Add {
     /// Shows a list of versions available to install.
     #[arg(short = 'S', long)]
     show: bool,
+    /// Never prompt to choose between equally matching assets, fail with the list of candidates instead.
+    #[arg(short, long, alias = "non-interactive")]
+    yes: bool,
+    /// Where the releases are published.
+    ///
+    /// Detected from the host when omitted, falling back to GitHub.
+    #[arg(long, value_enum)]
+    source: Option<SourceKind>,
+    /// Download URL template for the `template` source.
+    ///
+    /// May reference `{owner}`, `{repo}`, `{version}`, `{os}` and `{arch}`.
+    #[arg(long)]
+    url_template: Option<String>,
+    /// URL returning the latest version as plain text, for the `template` source.
+    #[arg(long)]
+    latest_url: Option<String>,
+    /// Regular expression matching the tags of long term support releases, for `name@lts`.
+    #[arg(long)]
+    lts_pattern: Option<String>,
+    /// Directory to install the binaries to, instead of the configured `bin_dir`.
+    #[arg(long, value_name = "DIR")]
+    bin_dir: Option<PathBuf>,
+    /// Install for another platform, which is remembered for `update`.
+    #[command(flatten)]
+    target: Target,
 }
 
 pub struct Patterns {
@@ -56,23 +90,13 @@ pub struct Patterns {
     pub file: Option<String>,
 }
 
-async fn repository_releases(owner: &'_ str, repository: &'_ str, pre_release: bool) -> anyhow::Result<Vec<String>> {
-    Ok(octocrab::instance()
-        .repos(owner, repository)
-        .releases()
-        .list()
-        .per_page(100)
-        .send()
+async fn repository_releases(source: &'_ Source, owner: &'_ str, repository: &'_ str, pre_release: bool) -> anyhow::Result<Vec<String>> {
+    Ok(source
+        .releases(owner, repository)
         .await?
-        .items
         .iter()
-        .filter_map(|release| {
-            if pre_release && release.prerelease {
-                None
-            } else {
-                Some(release.tag_name.clone())
-            }
-        })
+        .filter(|release| !release.draft && (pre_release || !release.prerelease))
+        .map(|release| release.tag_name.clone())
         .collect())
 }
 
@@ -80,19 +104,38 @@ impl RunCommand for Add {
     //
     async fn run(self) -> Result<()> {
         let mut packages = Config::load()?;
-        let system = System::default();
+        let system = System::default().with_libc(packages.libc);
 
         let split_name: Vec<&str> = self.name.split('@').collect();
 
         let urlish = if split_name.len() > 1 { split_name[0].to_string() } else { self.name.clone() };
 
-        let urlish = if urlish.contains("github.com") {
+        // Anything that starts with a host name is taken as-is, otherwise assume GitHub.
+        let urlish = if urlish.contains("://") || urlish.starts_with("git@") {
             urlish
+        } else if urlish.split('/').next().is_some_and(|host| host.contains('.')) {
+            format!("https://{urlish}")
         } else {
             format!("https://github.com/{urlish}")
         };
 
-        let url = GitUrl::parse(&urlish).expect("Couldn't parse as a GitHub URL!");
+        let url = GitUrl::parse(&urlish).expect("Couldn't parse as a repository URL!");
+
+        let host = url.host.clone().unwrap_or_else(|| "github.com".to_string());
+        let source_kind = self
+            .source
+            .or_else(|| packages.hosts.get(&host).and_then(|settings| settings.source))
+            .or_else(|| SourceKind::detect(&host))
+            .unwrap_or_default();
+
+        // A requirement such as `cli/cli@^2` is kept with the package, and resolved the same way `update` does.
+        // So is a channel (`@stable`, `@lts`), and `--pre-release` without a version follows the pre-release channel.
+        let requirement = match split_name.get(1) {
+            Some(v) if version::is_requirement(v) => Some((*v).to_string()),
+            Some(v) if matches!(version::parse(v), Version::Stable | Version::PreRelease | Version::Lts) => Some(version::parse(v).as_tag()),
+            None if self.pre_release && !self.show => Some(Version::PreRelease.as_tag()),
+            _ => None,
+        };
 
         let organization = url.owner.expect("Couldn't find an organization!");
         let repository = url.name;
@@ -107,44 +150,58 @@ impl RunCommand for Add {
         let file_pattern = &patterns.file.unwrap_or_else(|| alias.clone());
 
         info!("Organization `{organization}`, Repo `{repository}`, Alias `{alias}`, Pattern `{asset_pattern}`, Filter `{file_pattern}`");
+        info!("Source `{source_kind}`, Host `{host}`");
+
+        let package = self.target.apply(Package {
+            source: (source_kind != SourceKind::GitHub).then_some(source_kind),
+            host: (source_kind != SourceKind::Template && Some(host.as_str()) != source_kind.default_host()).then_some(host),
+            url_template: self.url_template,
+            latest_url: self.latest_url,
+            binaries: self.binaries,
+            version: requirement.clone(),
+            lts_pattern: self.lts_pattern,
+            bin_dir: self.bin_dir,
+            ..Package::new(&format!("{organization}/{repository}"), &alias, asset_pattern, file_pattern)
+        });
+
+        let source = Source::for_package(&packages, &package, &system)?;
+
+        let parsed_version = if requirement.is_some() {
+            None
+        } else if split_name.len() > 1 {
+            Some(version::parse(split_name[1]))
+        } else if self.show {
+            let versions = repository_releases(&source, &organization, &repository, self.pre_release).await?;
+
+            if versions.is_empty() {
+                return Err(CommandError::ReleaseNotFound(self.name.clone()));
+            }
 
-        let version: String = if split_name.len() > 1 {
-            split_name[1].to_string()
+            let reader = SkimItemReader::default().of_bufread(Cursor::new(versions.join("\n")));
+
+            let version: String = Skim::run_with(
+                &SkimOptionsBuilder::default()
+                    .color(Some(crate::config::skim_colors().to_string()))
+                    .height("50%".to_string())
+                    .multi(true)
+                    .reverse(true)
+                    .build()
+                    .expect("Unable to build SkimOptionsBuilder"),
+                Some(reader),
+            )
+            .map(|items| items.selected_items.iter().map(|item| item.text().to_string()).collect())
+            .unwrap_or_default();
+
+            Some(version::parse(&version))
         } else {
-            let versions = repository_releases(&organization, &repository, self.pre_release).await?;
-
-            if self.show {
-                let reader = SkimItemReader::default().of_bufread(Cursor::new(versions.join("\n")));
-
-                Skim::run_with(
-                    &SkimOptionsBuilder::default()
-                        .color(Some(crate::config::skim_colors().to_string()))
-                        .height("50%".to_string())
-                        .multi(true)
-                        .reverse(true)
-                        .build()
-                        .expect("Unable to build SkimOptionsBuilder"),
-                    Some(reader),
-                )
-                .map(|items| items.selected_items.iter().map(|item| item.text().to_string()).collect())
-                .unwrap_or_default()
-            } else {
-                match versions.first() {
-                    Some(version) => version.into(),
-                    None => return Err(CommandError::ReleaseNotFound(self.name.clone())),
-                }
-            }
+            Some(Version::Latest)
         };
 
-        let parsed_version = version::parse(&version);
-
-        let package = Package::new(&format!("{organization}/{repository}"), &alias, asset_pattern, file_pattern);
-
         let s = spinner();
 
         s.set_message(format!("⊙ Installing {} ...", &package.name));
 
-        match install::install_release(&mut packages, &package, &system, Some(parsed_version), self.show).await {
+        match install::install_release(&mut packages, &package, &system, parsed_version, !self.yes).await {
             Ok(()) => s.finish_with_message(format!("Installed {} successfully!", &package.name)),
             Err(e) => {
                 s.finish();
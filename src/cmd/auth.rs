@@ -0,0 +1,171 @@
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal};
+
+use anyhow::anyhow;
+use clap::{Args, Subcommand};
+use console::{style, Term};
+use serde::Serialize;
+use tabled::{
+    settings::{object::Columns, style::Style, Color, Modify},
+    Table, Tabled,
+};
+
+use crate::{
+    auth::{self, TokenSource},
+    cli::{Result, RunCommand},
+    config::Config,
+    output,
+    source::SourceKind,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct Auth {
+    #[command(subcommand)]
+    command: AuthCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum AuthCommand {
+    /// Save a token for a host, in the OS keyring or a file only you can read when there is no keyring.
+    Login {
+        /// Host the token is for.
+        #[arg(long, default_value = "github.com")]
+        host: String,
+        /// Read the token from stdin instead of prompting for it, e.g. `gh auth token | released auth login --with-token`.
+        #[arg(long)]
+        with_token: bool,
+    },
+    /// Remove the token saved for a host.
+    Logout {
+        /// Host to remove the token for.
+        #[arg(long, default_value = "github.com")]
+        host: String,
+    },
+    /// Show which token is used for each configured host, and where it was found.
+    Status,
+}
+
+#[derive(Tabled, Serialize)]
+struct HostStatus {
+    #[tabled(rename = "Host")]
+    host: String,
+    #[tabled(rename = "Source")]
+    source: String,
+    #[tabled(rename = "Token")]
+    token: String,
+}
+
+/// Enough of a token to tell which one it is.
+fn mask(token: &'_ str) -> String {
+    let prefix: String = token.chars().take(4).collect();
+
+    format!("{prefix}{}", "*".repeat(token.chars().count().saturating_sub(4).min(12)))
+}
+
+fn read_token(host: &'_ str, with_token: bool) -> anyhow::Result<String> {
+    let token = if with_token || !io::stdin().is_terminal() {
+        let mut line = String::new();
+
+        io::stdin().read_line(&mut line)?;
+
+        line
+    } else {
+        let term = Term::stderr();
+
+        term.write_str(&format!("Paste a token for {host}: "))?;
+        term.read_secure_line()?
+    };
+
+    let token = token.trim().to_string();
+
+    if token.is_empty() {
+        return Err(anyhow!("No token given for {host}"));
+    }
+
+    Ok(token)
+}
+
+impl RunCommand for Auth {
+    //
+    async fn run(self) -> Result<()> {
+        match self.command {
+            AuthCommand::Login { host, with_token } => {
+                let token = read_token(&host, with_token)?;
+
+                let saved = auth::save(&host, &token)?;
+
+                println!("{} Saved the token for {host} in {saved}", style("󰄴").green());
+
+                Ok(())
+            }
+            AuthCommand::Logout { host } => {
+                if auth::remove(&host)? {
+                    println!("{} Removed the token for {host}", style("󰄴").green());
+                } else {
+                    println!("No token was saved for {host}");
+                }
+
+                Ok(())
+            }
+            AuthCommand::Status => {
+                let config = Config::load()?;
+
+                let hosts: BTreeSet<String> = std::iter::once("github.com".to_string())
+                    .chain(config.hosts.keys().cloned())
+                    .chain(config.packages().values().filter_map(|package| package.host().map(str::to_string)))
+                    .collect();
+
+                let statuses: Vec<HostStatus> = hosts
+                    .into_iter()
+                    .map(|host| {
+                        let settings = config.hosts.get(&host);
+                        let kind = settings
+                            .and_then(|settings| settings.source)
+                            .or_else(|| SourceKind::detect(&host))
+                            .unwrap_or_default();
+
+                        let found = settings
+                            .and_then(|settings| settings.token.clone())
+                            .map(|token| (token, TokenSource::Config))
+                            .or_else(|| auth::find(&host, auth::variables(kind, &host)));
+
+                        match found {
+                            Some((token, source)) => HostStatus {
+                                host,
+                                source: source.to_string(),
+                                token: mask(&token),
+                            },
+                            None => HostStatus {
+                                host,
+                                source: "-".to_string(),
+                                token: String::new(),
+                            },
+                        }
+                    })
+                    .collect();
+
+                if output::is_structured() {
+                    return Ok(output::print("hosts", &statuses)?);
+                }
+
+                println!("\n{}", create_table(&statuses));
+
+                Ok(())
+            }
+        }
+    }
+}
+
+fn create_table(data: &[HostStatus]) -> Table {
+    let theme = Style::modern().remove_top().remove_bottom().remove_left().remove_right();
+
+    let mut table = Table::builder(data).build();
+
+    table
+        .with(theme)
+        .with(Modify::new(Columns::single(0)).with(Color::FG_WHITE))
+        .with(Modify::new(Columns::single(1)).with(Color::FG_CYAN))
+        .with(Modify::new(Columns::single(2)).with(Color::FG_BLUE));
+
+    table
+}
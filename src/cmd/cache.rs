@@ -0,0 +1,125 @@
+use std::time::{Duration, SystemTime};
+
+use clap::{Args, Subcommand};
+use indicatif::{HumanBytes, HumanDuration};
+use tabled::{
+    settings::{object::Columns, style::Style, Color, Modify},
+    Table, Tabled,
+};
+
+use crate::{
+    cache::{self, Entry},
+    cli::{Result, RunCommand},
+    output,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct Cache {
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheCommand {
+    /// List the cached release assets.
+    #[clap(alias = "ls")]
+    List,
+    /// Remove all cached release assets and API responses.
+    Clean,
+    /// Remove cached release assets and API responses which haven't been used for a while.
+    Prune {
+        /// How long an asset may go unused, e.g. `30d` or `12h`.
+        #[arg(long, value_parser = humantime::parse_duration, default_value = "30d")]
+        older_than: Duration,
+    },
+}
+
+#[derive(Tabled)]
+struct Cached<'a> {
+    #[tabled(rename = "Asset")]
+    name: &'a str,
+    #[tabled(rename = "Size")]
+    size: String,
+    #[tabled(rename = "Last Used")]
+    last_used: String,
+    #[tabled(rename = "SHA-256")]
+    sha256: &'a str,
+}
+
+fn unused_for(entry: &'_ Entry) -> Duration {
+    SystemTime::now().duration_since(entry.last_used()).unwrap_or_default()
+}
+
+fn report(removed: &'_ [Entry], (responses, responses_size): (usize, u64)) -> anyhow::Result<()> {
+    if output::is_structured() {
+        return output::print("removed", removed);
+    }
+
+    let freed: u64 = removed.iter().map(|entry| entry.size).sum::<u64>() + responses_size;
+
+    println!(
+        "Removed {} cached assets and {responses} API responses, freeing {}",
+        removed.len(),
+        HumanBytes(freed)
+    );
+
+    Ok(())
+}
+
+impl RunCommand for Cache {
+    //
+    async fn run(self) -> Result<()> {
+        match self.command {
+            CacheCommand::List => {
+                let entries = cache::entries()?;
+
+                if output::is_structured() {
+                    return Ok(output::print("assets", &entries)?);
+                }
+
+                if entries.is_empty() {
+                    println!("The download cache is empty.");
+                    return Ok(());
+                }
+
+                let lines: Vec<Cached<'_>> = entries
+                    .iter()
+                    .map(|entry| Cached {
+                        name: &entry.name,
+                        size: HumanBytes(entry.size).to_string(),
+                        last_used: format!("{} ago", HumanDuration(unused_for(entry))),
+                        sha256: &entry.sha256[..entry.sha256.len().min(12)],
+                    })
+                    .collect();
+
+                let total: u64 = entries.iter().map(|entry| entry.size).sum();
+
+                println!("Cached assets:");
+                println!("\n{}", create_table(&lines));
+                println!("\nTotal: {}", HumanBytes(total));
+
+                Ok(())
+            }
+            CacheCommand::Clean => Ok(report(&cache::remove(|_| true)?, cache::remove_responses(None)?)?),
+            CacheCommand::Prune { older_than } => Ok(report(
+                &cache::remove(|entry| unused_for(entry) > older_than)?,
+                cache::remove_responses(SystemTime::now().checked_sub(older_than))?,
+            )?),
+        }
+    }
+}
+
+fn create_table(data: &[Cached<'_>]) -> Table {
+    let theme = Style::modern().remove_top().remove_bottom().remove_left().remove_right();
+
+    let mut table = Table::builder(data).build();
+
+    table
+        .with(theme)
+        .with(Modify::new(Columns::single(0)).with(Color::FG_WHITE))
+        .with(Modify::new(Columns::single(1)).with(Color::FG_GREEN))
+        .with(Modify::new(Columns::single(2)).with(Color::FG_CYAN))
+        .with(Modify::new(Columns::single(3)).with(Color::FG_BLUE));
+
+    table
+}
@@ -1,14 +1,18 @@
+use std::path::PathBuf;
+
 use clap::Args;
 use serde::Serialize;
 use tabled::{
     settings::{object::Columns, style::Style, Color, Modify},
     Table, Tabled,
 };
+use tracing::warn;
 
 use crate::{
     cli::{Result, RunCommand},
     config::Config,
     errors::CommandError,
+    output,
 };
 
 #[derive(Debug, Clone, Args)]
@@ -24,6 +28,10 @@ struct Installed<'a> {
     path: &'a str,
     #[tabled(rename = "Repository")]
     repository: String,
+    /// Only in structured output, the table would get too wide.
+    #[tabled(skip)]
+    #[serde(skip_serializing_if = "<[PathBuf]>::is_empty")]
+    binaries: &'a [PathBuf],
 }
 
 impl RunCommand for List {
@@ -38,23 +46,29 @@ impl RunCommand for List {
         let mut lines = Vec::with_capacity(config.installed.len());
 
         for (name, installed) in &config.installed {
-            let Some(package) = config.packages.get(&installed.name) else {
-                println!("Package {} not found in the package list.", &installed.name);
+            let Some(package) = config.packages().get(&installed.name) else {
+                warn!("Package {} not found in the package list.", &installed.name);
                 continue;
             };
 
             lines.push(Installed {
-                repository: format!("https://github.com/{}", &package.name),
+                repository: package.repository_url(),
                 alias: name,
                 version: &installed.version,
                 path: installed.path.to_str().expect("Unable to convert path to string."),
+                binaries: &installed.binaries,
             });
         }
 
         lines.sort();
 
+        if output::is_structured() {
+            return Ok(output::print("packages", &lines)?);
+        }
+
         println!("Installed packages:");
         println!("\n{}", create_table(&lines));
+        println!("\nBinaries are installed to {}", config.bin_dir(None)?.display());
 
         Ok(())
     }
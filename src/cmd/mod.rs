@@ -1,4 +1,9 @@
 pub mod add;
+pub mod auth;
+pub mod cache;
 pub mod list;
+pub mod outdated;
 pub mod remove;
+pub mod rollback;
+pub mod sync;
 pub mod update;
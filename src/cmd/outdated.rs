@@ -0,0 +1,164 @@
+use clap::Args;
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use tabled::{
+    settings::{object::Columns, style::Style, Color, Modify},
+    Table, Tabled,
+};
+use tracing::error;
+
+use crate::{
+    cli::{Result, RunCommand},
+    config::{Config, Package},
+    errors::CommandError,
+    install, output,
+    source::{Release, Source},
+    spinner::spinner,
+    system::System,
+    version,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct Outdated {
+    /// Which package to check, when omitted all packages will be checked.
+    pub only: Option<String>,
+    /// How many packages to check at the same time.
+    #[arg(short, long, default_value_t = 8)]
+    pub jobs: usize,
+}
+
+#[derive(Tabled, Serialize, PartialEq, PartialOrd, Eq, Ord)]
+struct Upgrade {
+    #[tabled(rename = "Alias")]
+    alias: String,
+    #[tabled(rename = "Installed")]
+    installed: String,
+    #[tabled(rename = "Available")]
+    available: String,
+    #[tabled(rename = "Released")]
+    released: String,
+    #[tabled(rename = "Release Notes")]
+    notes: String,
+}
+
+/// Resolve the version `update` would install for `package`, without downloading anything.
+///
+/// `latest` is the package's latest release when it was already looked up in a batch.
+async fn available(config: &'_ Config, package: &'_ Package, system: &'_ System, latest: Option<Release>) -> anyhow::Result<Option<Upgrade>> {
+    let release = if let Some(release) = latest {
+        release
+    } else {
+        let source = Source::for_package(config, package, system)?;
+
+        match install::resolve_version(&source, package).await? {
+            (_, Some(release)) => release,
+            (version, None) => install::release_for_repository(&source, package, &version).await?,
+        }
+    };
+
+    let available = version::parse(&release.tag_name);
+
+    let installed = config.installed.get(&package.alias).map(|installed| installed.version.clone());
+
+    if installed.as_deref() == Some(available.as_tag().as_str()) {
+        return Ok(None);
+    }
+
+    Ok(Some(Upgrade {
+        alias: package.alias.clone(),
+        installed: installed.unwrap_or_else(|| "-".to_string()),
+        available: available.as_tag(),
+        released: release
+            .published_at
+            .as_deref()
+            .and_then(|date| date.split('T').next())
+            .unwrap_or_default()
+            .to_string(),
+        notes: release.html_url.unwrap_or_default(),
+    }))
+}
+
+impl RunCommand for Outdated {
+    //
+    async fn run(self) -> Result<()> {
+        let config = Config::load()?;
+        let system = System::default().with_libc(config.libc);
+
+        let s = if output::is_structured() { ProgressBar::hidden() } else { spinner() };
+
+        s.set_message("⊙ Checking for package updates ...");
+
+        let packages: Vec<(&String, &Package)> = config
+            .packages()
+            .iter()
+            .filter(|(name, _)| self.only.as_ref().is_none_or(|o| *name == o))
+            .collect();
+
+        let mut latest = install::latest_github_releases(&config, &packages.iter().map(|(_, package)| *package).collect::<Vec<_>>()).await;
+
+        let results: Vec<(String, anyhow::Result<Option<Upgrade>>)> = stream::iter(packages)
+            .map(|(name, package)| {
+                let (config, system, latest) = (&config, &system, latest.remove(&package.name));
+
+                async move { (name.clone(), available(config, package, system, latest).await) }
+            })
+            .buffer_unordered(self.jobs.max(1))
+            .collect()
+            .await;
+
+        s.finish_and_clear();
+
+        let mut lines = Vec::with_capacity(results.len());
+        let mut failed = 0;
+
+        for (name, result) in results {
+            match result {
+                Ok(Some(available)) => lines.push(available),
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Unable to check {name}: {e}");
+                    failed += 1;
+                }
+            }
+        }
+
+        lines.sort();
+
+        if output::is_structured() {
+            output::print("updates", &lines)?;
+        } else if lines.is_empty() {
+            if failed == 0 {
+                println!("All packages are up to date.");
+            }
+        } else {
+            println!("Available updates:");
+            println!("\n{}", create_table(&lines));
+        }
+
+        // Packages which couldn't be checked may be out of date too, so that's reported over any updates.
+        if failed > 0 {
+            Err(CommandError::CheckFailed { count: failed })
+        } else if lines.is_empty() {
+            Ok(())
+        } else {
+            Err(CommandError::UpdatesAvailable { count: lines.len() })
+        }
+    }
+}
+
+fn create_table(data: &[Upgrade]) -> Table {
+    let theme = Style::modern().remove_top().remove_bottom().remove_left().remove_right();
+
+    let mut table = Table::builder(data).build();
+
+    table
+        .with(theme)
+        .with(Modify::new(Columns::single(0)).with(Color::FG_WHITE))
+        .with(Modify::new(Columns::single(1)).with(Color::FG_YELLOW))
+        .with(Modify::new(Columns::single(2)).with(Color::FG_GREEN))
+        .with(Modify::new(Columns::single(3)).with(Color::FG_CYAN))
+        .with(Modify::new(Columns::single(4)).with(Color::FG_BLUE));
+
+    table
+}
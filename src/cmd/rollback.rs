@@ -0,0 +1,107 @@
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use clap::Args;
+use tracing::{debug, warn};
+
+use crate::{
+    cli::{Result, RunCommand},
+    config::{Config, InstalledPackage},
+    errors::CommandError,
+    history,
+    transaction::Transaction,
+    version,
+};
+
+#[derive(Debug, Clone, Args)]
+pub struct Rollback {
+    /// Name of the package to roll back.
+    name: String,
+    /// Version to restore, defaults to the previously installed version.
+    #[arg(long)]
+    to: Option<String>,
+}
+
+impl RunCommand for Rollback {
+    //
+    async fn run(self) -> Result<()> {
+        let mut config = Config::load()?;
+
+        let Some(current) = config.installed.get(&self.name).cloned() else {
+            return Err(CommandError::PackageNotFound { name: self.name.clone() });
+        };
+
+        let position = match &self.to {
+            Some(to) => current
+                .history
+                .iter()
+                .position(|previous| version::parse(&previous.installed.version) == version::parse(to)),
+            None => (!current.history.is_empty()).then_some(0),
+        };
+
+        let Some(position) = position else {
+            return Err(CommandError::NoPreviousVersion { name: self.name.clone() });
+        };
+
+        let mut older = current.history.clone();
+        let target = older.remove(position);
+
+        // Keep the current version around too, so the rollback itself can be undone.
+        let saved = history::save(&self.name, &current)?;
+
+        let mut transaction = Transaction::default();
+
+        for (destination, saved) in target.saved_files() {
+            if !saved.exists() {
+                warn!("{saved:?} is missing from the history, skipping");
+                continue;
+            }
+
+            debug!("Restoring {saved:?} to {destination:?}");
+
+            let mode = fs::metadata(&saved).map_err(anyhow::Error::from)?.permissions().mode();
+
+            transaction.install(&saved, destination, mode)?;
+        }
+
+        let restored_files: Vec<_> = target.installed.files().cloned().collect();
+
+        let history = history::rotate(saved, older);
+
+        config.installed.insert(
+            self.name.clone(),
+            InstalledPackage {
+                history: history.clone(),
+                ..target.installed.clone()
+            },
+        );
+
+        // Versions installed before the lockfile existed have nothing to lock to.
+        match (&target.installed.locked, &current.locked) {
+            (Some(locked), _) => config.lock(&self.name, locked.clone()),
+            (None, Some(current)) => config.unlock(&self.name, &current.target),
+            (None, None) => {}
+        }
+
+        transaction.check_interrupted()?;
+
+        config.save()?;
+
+        transaction.commit();
+
+        // Anything only shipped with the version being replaced goes away.
+        for path in current.files().filter(|path| !restored_files.contains(path) && path.exists()) {
+            debug!("Removing {path:?}");
+
+            if fs::remove_file(path).is_err() {
+                return Err(CommandError::FileDelete { file_name: path.clone() });
+            }
+        }
+
+        history::prune(&self.name, &history)?;
+
+        println!("Rolled back '{}' from {} to {}", &self.name, &current.version, &target.installed.version);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt::Display;
+
+use clap::Args;
+use console::style;
+use indicatif::ProgressBar;
+use serde::Serialize;
+use tracing::error;
+
+use crate::{
+    cache,
+    cli::{Result, RunCommand},
+    config::{Config, Package},
+    errors::CommandError,
+    install, output,
+    source::Source,
+    spinner::spinner,
+    system::System,
+    version,
+};
+
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Args)]
+pub struct Sync {
+    /// Also remove installed packages which are no longer in the config.
+    #[arg(long)]
+    prune: bool,
+    /// Only show what would change, without installing or removing anything.
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Install exactly the release assets recorded in `released.lock`, failing on any checksum mismatch.
+    #[arg(long)]
+    locked: bool,
+    /// Never prompt to choose between equally matching assets, fail with the list of candidates instead.
+    #[arg(short, long, alias = "non-interactive")]
+    yes: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    /// In the config but not installed.
+    Install,
+    /// Installed, but outside of the version requirement, not the locked version or with files missing.
+    Reinstall,
+    /// Installed but no longer in the config, only acted on with `--prune`.
+    Remove,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Install => write!(f, "install"),
+            Action::Reinstall => write!(f, "reinstall"),
+            Action::Remove => write!(f, "remove"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Change {
+    alias: String,
+    action: Action,
+    reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Work out what needs to change for the installed packages to match the config, or the lockfile when `locked`.
+fn plan(config: &'_ Config, system: &'_ System, locked: bool) -> Vec<(Change, Option<Package>)> {
+    let mut changes = Vec::new();
+
+    for package in config.packages().values() {
+        let Some(installed) = config.installed.get(&package.alias) else {
+            changes.push((
+                Change {
+                    alias: package.alias.clone(),
+                    action: Action::Install,
+                    reason: "not installed".to_string(),
+                    error: None,
+                },
+                Some(package.clone()),
+            ));
+            continue;
+        };
+
+        let lock = config.lock_for(&package.alias, &system.for_package(package).target()).filter(|_| locked);
+
+        let reason = if let Some(missing) = std::iter::once(&installed.path).chain(&installed.binaries).find(|binary| !binary.exists()) {
+            Some(format!("{missing:?} is missing"))
+        } else if let Some(lock) = lock {
+            (version::parse(&lock.tag).as_tag() != installed.version).then(|| format!("{} is locked to {}", installed.version, lock.tag))
+        } else {
+            package
+                .version
+                .as_ref()
+                .filter(|requirement| version::drifted(requirement, &installed.version))
+                .map(|requirement| format!("{} doesn't match '{requirement}'", installed.version))
+        };
+
+        if let Some(reason) = reason {
+            changes.push((
+                Change {
+                    alias: package.alias.clone(),
+                    action: Action::Reinstall,
+                    reason,
+                    error: None,
+                },
+                Some(package.clone()),
+            ));
+        }
+    }
+
+    for (alias, installed) in &config.installed {
+        if config.packages().get(&installed.name).is_some_and(|package| package.alias == *alias) {
+            continue;
+        }
+
+        changes.push((
+            Change {
+                alias: alias.clone(),
+                action: Action::Remove,
+                reason: format!("{} isn't in the config", installed.name),
+                error: None,
+            },
+            None,
+        ));
+    }
+
+    changes
+}
+
+/// Install `package` even if the resolved version is already recorded, since its files may be gone.
+async fn reinstall(config: &'_ mut Config, package: &'_ Package, system: &'_ System, locked: bool, interactive: bool) -> anyhow::Result<()> {
+    let source = Source::for_package(config, package, system)?;
+
+    let prepared = if locked {
+        let Some(lock) = config.lock_for(&package.alias, &system.for_package(package).target()) else {
+            return Err(CommandError::NotLocked { name: package.alias.clone() }.into());
+        };
+
+        install::prepare_locked(&source, None, package, lock).await?
+    } else {
+        install::prepare_release(&source, None, package, system, None, interactive).await?
+    };
+
+    prepared.install(config)
+}
+
+impl RunCommand for Sync {
+    //
+    async fn run(self) -> Result<()> {
+        let mut config = Config::load()?;
+        let system = System::default().with_libc(config.libc);
+
+        let structured = output::is_structured();
+
+        // Offline, the lockfile says which cached assets to install.
+        let locked = self.locked || cache::is_offline();
+
+        let mut changes = plan(&config, &system, locked);
+
+        if !self.prune {
+            let (orphans, rest): (Vec<_>, Vec<_>) = changes.into_iter().partition(|(change, _)| change.action == Action::Remove);
+
+            if !structured {
+                for (change, _) in orphans {
+                    println!(
+                        "{} {} is installed but not in the config, use --prune to remove it",
+                        style("").yellow(),
+                        change.alias
+                    );
+                }
+            }
+
+            changes = rest;
+        }
+
+        if changes.is_empty() {
+            if structured {
+                output::print::<Change>("changes", &[])?;
+            } else {
+                println!("Everything is in sync.");
+            }
+
+            return Ok(());
+        }
+
+        if self.dry_run {
+            let changes: Vec<Change> = changes.into_iter().map(|(change, _)| change).collect();
+
+            if structured {
+                output::print("changes", &changes)?;
+            } else {
+                for change in &changes {
+                    println!("Would {} {}: {}", change.action, change.alias, change.reason);
+                }
+            }
+
+            return Ok(());
+        }
+
+        let mut results = Vec::with_capacity(changes.len());
+
+        // One at a time, installing and removing both need the config.
+        for (mut change, package) in changes {
+            let s = if structured { ProgressBar::hidden() } else { spinner() };
+
+            s.set_message(format!("⊙ {} {} ...", change.action, change.alias));
+
+            let result = match &package {
+                Some(package) => reinstall(&mut config, package, &system, locked, !self.yes).await,
+                None => install::uninstall(&mut config, &change.alias).and_then(|()| config.save()),
+            };
+
+            match result {
+                Ok(()) => s.finish_with_message(format!("{} {} ({})", style("󰄴").green(), change.alias, change.action)),
+                Err(e) => {
+                    s.finish_with_message(format!("{} {}: {e}", style("").red(), change.alias));
+                    error!("Unable to {} {}: {e:?}", change.action, change.alias);
+
+                    change.error = Some(e.to_string());
+                }
+            }
+
+            results.push(change);
+        }
+
+        if structured {
+            output::print("changes", &results)?;
+        }
+
+        match results.iter().filter(|change| change.error.is_some()).count() {
+            0 => Ok(()),
+            count => Err(CommandError::SyncFailed { count }),
+        }
+    }
+}
@@ -1,58 +1,214 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::Instant;
 
 use clap::Args;
 use console::style;
+use futures::stream::{self, StreamExt};
 use indicatif::HumanDuration;
 use pluralizer::pluralize;
+use serde::Serialize;
+use tracing::error;
 
 use crate::{
+    cache,
     cli::{Result, RunCommand},
-    config::Config,
+    cmd::outdated::Outdated,
+    config::{Config, Package},
     errors::CommandError,
     install,
+    output::{self, OutputFormat},
+    source::{Release, Source},
     spinner::spinner,
-    system::System,
+    system::{System, Target},
 };
 
 #[derive(Debug, Clone, Args)]
 pub struct Update {
     /// Which package to update, when omitted all packages will be updated.
     only: Option<String>,
+    /// How many packages to check and download at the same time.
+    #[arg(short, long, default_value_t = 8)]
+    jobs: usize,
+    /// Only report the available updates, the same as `outdated`.
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Install exactly the release assets recorded in `released.lock`.
+    #[arg(long, conflicts_with = "dry_run")]
+    locked: bool,
+    /// Never prompt to choose between equally matching assets, fail with the list of candidates instead.
+    #[arg(short, long, alias = "non-interactive")]
+    yes: bool,
+    /// Install for another platform, only for this update.
+    #[command(flatten)]
+    target: Target,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Updated,
+    UpToDate,
+    Error,
+}
+
+/// Result of updating one package, for structured output.
+#[derive(Debug, Serialize)]
+struct Updated {
+    package: String,
+    status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Check and download concurrently, only holding the lock on `config` to read it and to install.
+///
+/// Returns the version which was installed.
+/// `latest` is the package's latest release when it was already looked up in a batch.
+async fn update_package(
+    config: &'_ Mutex<Config>,
+    package: &'_ Package,
+    system: &'_ System,
+    locked: bool,
+    latest: Option<&'_ Release>,
+    interactive: bool,
+) -> anyhow::Result<String> {
+    let (source, installed, lock) = {
+        let config = config.lock().expect("Config lock poisoned");
+
+        (
+            Source::for_package(&config, package, system)?,
+            config.installed.get(&package.alias).cloned(),
+            config.lock_for(&package.alias, &system.for_package(package).target()).cloned(),
+        )
+    };
+
+    let prepared = if locked {
+        let Some(lock) = lock else {
+            return Err(CommandError::NotLocked { name: package.alias.clone() }.into());
+        };
+
+        install::prepare_locked(&source, installed.as_ref(), package, &lock).await?
+    } else if let Some(release) = latest {
+        install::prepare_fetched(installed.as_ref(), package, system, release, interactive).await?
+    } else {
+        install::prepare_release(&source, installed.as_ref(), package, system, None, interactive).await?
+    };
+
+    let version = prepared.version().to_string();
+
+    let mut config = config.lock().expect("Config lock poisoned");
+
+    prepared.install(&mut config)?;
+
+    Ok(version)
 }
 
 impl RunCommand for Update {
     //
     async fn run(self) -> Result<()> {
-        let mut config = Config::load()?;
-        let system = System::default();
+        if self.dry_run {
+            return Outdated {
+                only: self.only,
+                jobs: self.jobs,
+            }
+            .run()
+            .await;
+        }
+
+        let config = Config::load()?;
+        let system = System::default().with_libc(config.libc);
 
         let started = Instant::now();
-        let mut count = 0;
 
-        println!("Checking for package updates ...\n");
+        let packages: Vec<(String, Package)> = config
+            .packages()
+            .iter()
+            .filter(|(name, _)| self.only.as_ref().is_none_or(|o| *name == o))
+            .map(|(name, package)| (name.clone(), self.target.apply(package.clone())))
+            .collect();
 
-        for (name, package) in &config.packages.clone() {
-            //
-            if self.only.as_ref().is_some_and(|o| name != o) {
-                continue;
-            }
+        let count = isize::try_from(packages.len()).unwrap_or(isize::MAX);
+
+        let structured = output::is_structured();
+
+        if !structured {
+            println!("Checking for package updates ...\n");
+        }
+
+        // Offline, the lockfile says which cached assets to install.
+        let locked = self.locked || cache::is_offline();
+
+        let latest = if locked {
+            HashMap::new()
+        } else {
+            install::latest_github_releases(&config, &packages.iter().map(|(_, package)| package).collect::<Vec<_>>()).await
+        };
 
-            count += 1;
+        let config = Mutex::new(config);
+        let interactive = !self.yes;
+        let results = stream::iter(packages)
+            .map(|(name, package)| {
+                let s = spinner();
+                let (config, system, latest) = (&config, &system, latest.get(&package.name));
 
-            let s = spinner();
+                async move {
+                    s.set_message(format!("⊙ Checking {name} ..."));
 
-            s.set_message(format!("⊙ Checking {name} ..."));
+                    let result = match update_package(config, &package, system, locked, latest, interactive).await {
+                        Ok(version) => {
+                            s.finish_with_message(format!("{} {} updated", style("󰄴").green(), &name));
 
-            match install::install_release(&mut config, package, &system, None, false).await {
-                Ok(()) => s.finish_with_message(format!("{} {} updated", style("󰄴").green(), &name)),
-                Err(e) if e.to_string() == CommandError::NoUpdateNeeded.to_string() => {
-                    s.finish_with_message(format!("{} {} is already up to date!", style("󰐾").blue(), &name));
+                            Updated {
+                                package: name,
+                                status: Status::Updated,
+                                version: Some(version),
+                                error: None,
+                            }
+                        }
+                        Err(e) if e.to_string() == CommandError::NoUpdateNeeded.to_string() => {
+                            s.finish_with_message(format!("{} {} is already up to date!", style("󰐾").blue(), &name));
+
+                            Updated {
+                                package: name,
+                                status: Status::UpToDate,
+                                version: None,
+                                error: None,
+                            }
+                        }
+                        Err(e) => {
+                            s.finish_with_message(format!("{} {name}: {:?}", style("").red(), e.to_string()));
+
+                            Updated {
+                                package: name,
+                                status: Status::Error,
+                                version: None,
+                                error: Some(e.to_string()),
+                            }
+                        }
+                    };
+
+                    // Stream results as they complete, the other formats need the whole list.
+                    if output::format() == OutputFormat::Ndjson {
+                        if let Err(e) = output::print_one(&result) {
+                            error!("Unable to print result: {e}");
+                        }
+                    }
+
+                    result
                 }
-                Err(e) => s.finish_with_message(format!("{} {:?}", style("").red(), e.to_string())),
-            }
-        }
+            })
+            .buffer_unordered(self.jobs.max(1))
+            .collect::<Vec<Updated>>()
+            .await;
 
-        println!("\n\nChecked for {} in {}", pluralize("update", count, true), HumanDuration(started.elapsed()));
+        if !structured {
+            println!("\n\nChecked for {} in {}", pluralize("update", count, true), HumanDuration(started.elapsed()));
+        } else if output::format() != OutputFormat::Ndjson {
+            output::print("updates", &results)?;
+        }
 
         Ok(())
     }
@@ -3,13 +3,19 @@ use serde::{Deserialize, Serialize};
 use serde_json::to_string_pretty;
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 use tracing::debug;
 
-use crate::errors::ConfigError;
+use crate::{
+    errors::ConfigError,
+    source::SourceKind,
+    system::{Libc, OperatingSystem, PlatformArchitecture},
+};
 
 static SKIM_COLORS: OnceLock<String> = OnceLock::new();
+static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
 
 pub fn skim_colors() -> &'static str {
     SKIM_COLORS.get_or_init(|| {
@@ -31,13 +37,43 @@ pub fn skim_colors() -> &'static str {
     })
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Where binaries are installed, defaults to `~/.local/bin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<PathBuf>,
+
+    /// Preferred C library, `gnu` or `musl`, when a release ships Linux assets for both.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub libc: Option<Libc>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hosts: BTreeMap<String, Host>,
+
+    /// Named package sets, e.g. `[profile.ci]`, used instead of the top level packages when selected.
+    #[serde(default, rename = "profile", skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+
+    // Use `packages()`, which takes the selected profile into account.
     #[serde(flatten)]
-    pub packages: BTreeMap<String, Package>,
+    packages: BTreeMap<String, Package>,
 
     #[serde(skip)]
     pub installed: BTreeMap<String, InstalledPackage>,
+
+    /// Resolved release assets from `released.lock`, keyed by alias and then target, e.g. `linux-x86_64-gnu`.
+    #[serde(skip)]
+    pub locked: BTreeMap<String, BTreeMap<String, LockedPackage>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Profile {
+    /// Where binaries are installed, overriding the global `bin_dir`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<PathBuf>,
+
+    #[serde(flatten)]
+    pub packages: BTreeMap<String, Package>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
@@ -51,6 +87,91 @@ pub struct Package {
 
     // #[serde(skip_serializing_if = "Option::is_none")]
     pub file_pattern: String,
+
+    /// SemVer requirement that updates must satisfy, e.g. `1.2.3`, `^1.4`, `~0.9` or `<2`.
+    ///
+    /// May also be a release channel: `stable`, `pre-release` or `lts`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Regular expression matching the tags of long term support releases, used by the `lts` channel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lts_pattern: Option<String>,
+
+    /// Binaries to install from the release archive, defaults to a single binary matching `file_pattern`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<Binary>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<Signature>,
+
+    /// Where releases are published, defaults to GitHub.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceKind>,
+
+    /// Host of the forge, when it isn't the default for `source`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+
+    /// Download URL template, for the `template` source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url_template: Option<String>,
+
+    /// URL returning the latest version as plain text, for the `template` source.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_url: Option<String>,
+
+    /// Operating system to install for, when it isn't the one released runs on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os: Option<OperatingSystem>,
+
+    /// Architecture to install for, when it isn't the one released runs on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arch: Option<PlatformArchitecture>,
+
+    /// C library to prefer, overriding the global `libc`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub libc: Option<Libc>,
+
+    /// Where to install the binaries, overriding the global `bin_dir`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "snake_case", default)]
+pub struct Binary {
+    /// File name inside the release archive.
+    pub name: String,
+
+    /// Name to install the binary as, defaults to `name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rename: Option<String>,
+}
+
+/// Policy used to verify that release assets were signed by the package maintainer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Signature {
+    /// Verify a `<asset>.minisig` signature with the given base64 public key.
+    Minisign { public_key: String },
+    /// Verify a `<asset>.bundle` / `<asset>.sigstore.json` keyless bundle with `cosign verify-blob`.
+    Cosign { identity: String, issuer: String },
+}
+
+/// Settings for a self-hosted forge, such as GitHub Enterprise Server.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "snake_case", default)]
+pub struct Host {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceKind>,
+
+    /// API base URL, defaults to the conventional location for the source (e.g. `https://<host>/api/v3`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
@@ -59,22 +180,102 @@ pub struct InstalledPackage {
     pub name: String,
     pub version: String,
     pub path: PathBuf,
+
+    /// Any additional binaries installed from the same release.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub binaries: Vec<PathBuf>,
+
+    /// Shell completions installed from the release archive.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub completions: Vec<PathBuf>,
+
+    /// Man pages installed from the release archive.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub man_pages: Vec<PathBuf>,
+
+    /// Previously installed versions, newest first, that can be restored with `rollback`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<PreviousVersion>,
+
+    /// The release asset this version was installed from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locked: Option<LockedPackage>,
+}
+
+/// The exact release asset installed for a package, as recorded in `released.lock`.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "snake_case", default)]
+pub struct LockedPackage {
+    pub name: String,
+    /// Platform the asset was chosen for, e.g. `linux-x86_64-gnu`.
+    pub target: String,
+    /// Release tag as published, e.g. `v1.2.3`.
+    pub tag: String,
+    pub asset: String,
+    pub url: String,
+    pub size: u64,
+    pub sha256: String,
 }
 
-pub fn bin_path() -> Result<PathBuf> {
-    #[allow(deprecated)]
-    let home_dir = std::env::home_dir().context("Get HOME directory")?;
+#[derive(Debug, Deserialize, Default)]
+struct Lockfile {
+    #[serde(default)]
+    package: BTreeMap<String, BTreeMap<String, LockedPackage>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(rename_all = "snake_case", default)]
+pub struct PreviousVersion {
+    /// Where copies of the files are kept.
+    pub directory: PathBuf,
+
+    #[serde(flatten)]
+    pub installed: InstalledPackage,
+}
 
-    let bin_path = home_dir.join(".local/bin/");
+/// Install root given with `--root`, which holds released's own files along with the installed ones.
+pub fn set_root(root: Option<PathBuf>) {
+    ROOT.get_or_init(|| root);
+}
+
+pub fn root() -> Option<&'static Path> {
+    ROOT.get().and_then(Option::as_deref)
+}
 
-    if !bin_path.exists() {
-        fs::create_dir_all(&bin_path).context("Creating `~/.local/bin`")?;
+/// Profile given with `--profile`, falling back to `RELEASED_PROFILE`.
+pub fn set_profile(profile: Option<String>) {
+    PROFILE.get_or_init(|| profile.or_else(|| std::env::var("RELEASED_PROFILE").ok().filter(|profile| !profile.is_empty())));
+}
+
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().and_then(Option::as_deref)
+}
+
+/// Each profile keeps its own state, lockfile and history, since they install different packages.
+pub fn profile_file_name(stem: &'_ str, extension: &'_ str) -> String {
+    match (profile(), extension) {
+        (Some(profile), "") => format!("{stem}-{profile}"),
+        (Some(profile), extension) => format!("{stem}-{profile}.{extension}"),
+        (None, "") => stem.to_string(),
+        (None, extension) => format!("{stem}.{extension}"),
     }
+}
 
-    Ok(bin_path.clone())
+/// Where config, state and history are kept under an install root.
+pub fn root_state_dir(root: &'_ Path) -> Result<PathBuf> {
+    let dir = root.join(".released");
+
+    match fs::create_dir_all(&dir) {
+        Ok(()) => Ok(dir),
+        Err(e) => Err(ConfigError::FailedToCreateDirectory { path: dir, source: e }.into()),
+    }
 }
 
 fn config_path() -> Result<PathBuf> {
+    if let Some(root) = root() {
+        return Ok(root_state_dir(root)?.join("config.toml"));
+    }
+
     let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get config directory")?;
 
     match xdg_dir.place_config_file("config.toml") {
@@ -88,9 +289,13 @@ fn config_path() -> Result<PathBuf> {
 }
 
 fn state_path() -> Result<PathBuf> {
+    if let Some(root) = root() {
+        return Ok(root_state_dir(root)?.join(profile_file_name("installed", "json")));
+    }
+
     let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get config directory")?;
 
-    match xdg_dir.place_state_file("installed.json") {
+    match xdg_dir.place_state_file(profile_file_name("installed", "json")) {
         Ok(path) => Ok(path),
         Err(e) => Err(ConfigError::FailedToCreateDirectory {
             path: xdg_dir.get_state_home(),
@@ -100,10 +305,29 @@ fn state_path() -> Result<PathBuf> {
     }
 }
 
+/// Kept next to `config.toml`, so both can be checked in together.
+fn lock_path() -> Result<PathBuf> {
+    if let Some(root) = root() {
+        return Ok(root_state_dir(root)?.join(profile_file_name("released", "lock")));
+    }
+
+    let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get config directory")?;
+
+    match xdg_dir.place_config_file(profile_file_name("released", "lock")) {
+        Ok(path) => Ok(path),
+        Err(e) => Err(ConfigError::FailedToCreateDirectory {
+            path: xdg_dir.get_config_home(),
+            source: e,
+        }
+        .into()),
+    }
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let config_file = config_path()?;
         let state_file = state_path()?;
+        let lock_file = lock_path()?;
 
         let mut config: Config = if config_file.exists() {
             debug!("Reading config file from {:?}", &config_file);
@@ -128,6 +352,28 @@ impl Config {
             Config::default()
         };
 
+        // Entries added by hand may leave out the name and alias, which the table key already gives.
+        let profiles = config.profiles.values_mut().map(|profile| &mut profile.packages);
+
+        for (key, package) in std::iter::once(&mut config.packages).chain(profiles).flatten() {
+            if package.name.is_empty() {
+                package.name.clone_from(key);
+            }
+
+            let Some((_, repository)) = package.name.rsplit_once('/') else {
+                return Err(ConfigError::DeserializationError {
+                    file_path: config_file,
+                    format: "TOML".to_string(),
+                    msg: format!("Package '{}' should be named `owner/repository`", package.name),
+                }
+                .into());
+            };
+
+            if package.alias.is_empty() {
+                package.alias = repository.to_string();
+            }
+        }
+
         config.installed = match fs::read_to_string(&state_file) {
             Ok(s) => match serde_json::from_str(&s) {
                 Ok(c) => c,
@@ -150,16 +396,107 @@ impl Config {
             }
         };
 
+        config.locked = match fs::read_to_string(&lock_file) {
+            Ok(s) => match toml::from_str::<Lockfile>(&s) {
+                Ok(l) => l.package,
+                Err(e) => {
+                    return Err(ConfigError::DeserializationError {
+                        file_path: lock_file,
+                        format: "TOML".to_string(),
+                        msg: e.to_string(),
+                    }
+                    .into())
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                return Err(ConfigError::FileReadError {
+                    file_path: lock_file,
+                    source: e,
+                }
+                .into());
+            }
+        };
+
         Ok(config)
     }
 
+    /// The selected profile, if there is one.
+    pub fn profile(&self) -> Option<&Profile> {
+        profile().and_then(|name| self.profiles.get(name))
+    }
+
+    /// Packages of the selected profile, or the top level ones.
+    pub fn packages(&self) -> &BTreeMap<String, Package> {
+        static EMPTY: BTreeMap<String, Package> = BTreeMap::new();
+
+        match profile() {
+            Some(name) => self.profiles.get(name).map_or(&EMPTY, |profile| &profile.packages),
+            None => &self.packages,
+        }
+    }
+
+    /// The asset locked for `alias` on `target`, the lockfile may be shared with machines on other platforms.
+    pub fn lock_for(&self, alias: &'_ str, target: &'_ str) -> Option<&LockedPackage> {
+        self.locked.get(alias)?.get(target)
+    }
+
+    /// Lock `alias` to `locked` on its target, leaving the other platforms alone.
+    pub fn lock(&mut self, alias: &'_ str, locked: LockedPackage) {
+        self.locked.entry(alias.to_string()).or_default().insert(locked.target.clone(), locked);
+    }
+
+    /// Forget the asset locked for `alias` on `target`.
+    pub fn unlock(&mut self, alias: &'_ str, target: &'_ str) {
+        if let Some(targets) = self.locked.get_mut(alias) {
+            targets.remove(target);
+
+            if targets.is_empty() {
+                self.locked.remove(alias);
+            }
+        }
+    }
+
+    pub fn packages_mut(&mut self) -> &mut BTreeMap<String, Package> {
+        match profile() {
+            Some(name) => &mut self.profiles.entry(name.to_string()).or_default().packages,
+            None => &mut self.packages,
+        }
+    }
+
+    /// Where the binaries of `package` are installed.
+    ///
+    /// In order: the package's own `bin_dir`, `RELEASED_BIN_DIR`, the profile's `bin_dir`, the global `bin_dir`, then
+    /// `bin` under the install root or `~/.local/bin`.
+    pub fn bin_dir(&self, package: Option<&'_ Package>) -> Result<PathBuf> {
+        let configured = package
+            .and_then(|package| package.bin_dir.clone())
+            .or_else(|| std::env::var_os("RELEASED_BIN_DIR").filter(|dir| !dir.is_empty()).map(PathBuf::from))
+            .or_else(|| self.profile().and_then(|profile| profile.bin_dir.clone()))
+            .or_else(|| self.bin_dir.clone());
+
+        Ok(match (configured, root()) {
+            (Some(dir), _) => dir,
+            (None, Some(root)) => root.join("bin"),
+            (None, None) => PathBuf::from(std::env::var_os("HOME").context("Get HOME directory")?).join(".local/bin"),
+        })
+    }
+
+    /// The forge kind for a package, taking any `[hosts]` configuration into account.
+    pub fn source_kind(&self, package: &'_ Package) -> SourceKind {
+        package
+            .source
+            .or_else(|| package.host.as_ref().and_then(|host| self.hosts.get(host)).and_then(|host| host.source))
+            .unwrap_or_default()
+    }
+
     pub fn save(&self) -> Result<()> {
         let config_file = self::config_path()?;
         let state_file = self::state_path()?;
 
         debug!("Writing config file to {:?}", &config_file);
 
-        let config_toml = toml::to_string(&self.packages).context("Serializing config into TOML format")?;
+        let config_toml = toml::to_string(&self).context("Serializing config into TOML format")?;
 
         fs::write(&config_file, config_toml).context(format!("Writing config file: {}", config_file.display()))?;
 
@@ -171,10 +508,54 @@ impl Config {
 
         debug!("Wrote installed state to file {:?}", &state_file);
 
+        let lock_file = self::lock_path()?;
+
+        let lock = toml::to_string(&BTreeMap::from([("package", &self.locked)])).context("Serializing lockfile into TOML format")?;
+
+        fs::write(
+            &lock_file,
+            format!("# Generated by released, the exact release assets to install with `--locked`.\n\n{lock}"),
+        )
+        .context(format!("Writing lockfile: {}", lock_file.display()))?;
+
         Ok(())
     }
 }
 
+impl Binary {
+    pub fn installed_name(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl std::str::FromStr for Binary {
+    type Err = std::convert::Infallible;
+
+    /// Parse `name` or `name=rename`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.split_once('=') {
+            Some((name, rename)) => Self {
+                name: name.to_string(),
+                rename: Some(rename.to_string()),
+            },
+            None => Self {
+                name: s.to_string(),
+                rename: None,
+            },
+        })
+    }
+}
+
+impl InstalledPackage {
+    /// Every file installed for this package.
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.path)
+            .chain(&self.binaries)
+            .chain(&self.completions)
+            .chain(&self.man_pages)
+    }
+}
+
 impl Package {
     pub fn new(name: &'_ str, alias: &'_ str, asset_pattern: &'_ str, file_pattern: &'_ str) -> Self {
         Self {
@@ -182,6 +563,28 @@ impl Package {
             alias: alias.to_owned(),
             asset_pattern: asset_pattern.to_owned(),
             file_pattern: file_pattern.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// The (owner, repository) parts of the package name.
+    pub fn repository(&self) -> (&str, &str) {
+        self.name.rsplit_once('/').expect("Invalid package name")
+    }
+
+    pub fn source_kind(&self) -> SourceKind {
+        self.source.unwrap_or_default()
+    }
+
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref().or_else(|| self.source_kind().default_host())
+    }
+
+    pub fn repository_url(&self) -> String {
+        match (self.source_kind(), self.host()) {
+            (SourceKind::Template, _) => self.url_template.clone().unwrap_or_default(),
+            (_, Some(host)) => format!("https://{host}/{}", self.name),
+            (_, None) => self.name.clone(),
         }
     }
 }
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::stream::StreamExt;
+use reqwest::{
+    header::{self, HeaderValue},
+    Client, StatusCode, Url,
+};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+use crate::{errors::CommandError, source, spinner};
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+// `Authorization` headers for the hosts of private releases, keyed by host.
+static AUTHORIZATIONS: Mutex<Option<HashMap<String, HeaderValue>>> = Mutex::new(None);
+
+/// How patient to be with slow or flaky connections.
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    /// Time allowed to establish a connection.
+    pub connect_timeout: Duration,
+    /// Time allowed without receiving any data, before the attempt is retried.
+    pub read_timeout: Duration,
+    /// How many times a failed download is retried, resuming where it left off when the server allows it.
+    pub retries: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            retries: 5,
+        }
+    }
+}
+
+pub fn configure(settings: Settings) {
+    SETTINGS.get_or_init(|| settings);
+}
+
+fn settings() -> Settings {
+    SETTINGS.get().copied().unwrap_or_default()
+}
+
+fn client() -> Result<&'static Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let settings = settings();
+
+    let client = Client::builder()
+        .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(settings.connect_timeout)
+        .read_timeout(settings.read_timeout)
+        .build()
+        .context("Unable to build HTTP client")?;
+
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+/// Send `authorization` with downloads from `host`, so assets of private repositories can be fetched.
+///
+/// It's only sent to `host` itself, reqwest drops it when a download is redirected elsewhere, e.g. to a CDN.
+pub fn authorize(host: &'_ str, mut authorization: HeaderValue) {
+    authorization.set_sensitive(true);
+
+    AUTHORIZATIONS
+        .lock()
+        .expect("Authorization lock poisoned")
+        .get_or_insert_with(HashMap::new)
+        .insert(host.to_string(), authorization);
+}
+
+/// A GET request for `url`, with the token of its host if there is one.
+fn get(client: &'_ Client, url: &'_ Url) -> reqwest::RequestBuilder {
+    let authorization = url.host_str().and_then(|host| {
+        AUTHORIZATIONS
+            .lock()
+            .expect("Authorization lock poisoned")
+            .as_ref()
+            .and_then(|authorizations| authorizations.get(host).cloned())
+    });
+
+    match authorization {
+        Some(authorization) => client.get(url.clone()).header(header::AUTHORIZATION, authorization),
+        None => client.get(url.clone()),
+    }
+}
+
+/// Whether an attempt failed in a way that may succeed when tried again.
+fn is_transient(error: &'_ reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => is_transient_status(status),
+        None => error.is_timeout() || error.is_connect() || error.is_request() || error.is_body() || error.is_decode(),
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::REQUEST_TIMEOUT || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Exponential backoff: 1s, 2s, 4s ... up to a minute.
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(2u64.saturating_pow(attempt).min(60))
+}
+
+/// The outcome of one attempt at downloading the whole file.
+enum Attempt {
+    Done,
+    /// Failed part way, `received` bytes are on disk and can be resumed from.
+    Failed {
+        error: anyhow::Error,
+        transient: bool,
+    },
+}
+
+/// What has been downloaded so far.
+struct Partial {
+    file: tokio::fs::File,
+    hasher: Sha256,
+    received: u64,
+    /// `ETag` or `Last-Modified` of the first response, sent as `If-Range` so a changed file isn't resumed.
+    validator: Option<HeaderValue>,
+}
+
+impl Partial {
+    /// Throw away what was received, the next attempt starts from the beginning.
+    async fn restart(&mut self) -> Result<()> {
+        self.file.set_len(0).await?;
+        self.file.rewind().await?;
+
+        self.hasher = Sha256::new();
+        self.received = 0;
+
+        Ok(())
+    }
+}
+
+/// Download `url` into `directory`, returning the file path and its SHA-256 digest.
+///
+/// Transient failures are retried with exponential backoff, resuming from the bytes already received when the
+/// server supports range requests.
+pub async fn download(url: &Url, directory: &'_ Path) -> Result<(PathBuf, String)> {
+    let filename = source::file_name(url);
+
+    if filename.is_empty() {
+        return Err(CommandError::InvalidUrl(url.to_string()).into());
+    }
+
+    let destination = directory.join(&filename);
+
+    debug!("Creating destination directory {}", directory.display());
+
+    fs::create_dir_all(directory)?;
+
+    debug!("Downloading {filename} to {} ...", destination.display());
+
+    let settings = settings();
+    let bar = spinner::download_bar(&filename);
+
+    let mut partial = Partial {
+        file: tokio::fs::File::create(&destination).await?,
+        hasher: Sha256::new(),
+        received: 0,
+        validator: None,
+    };
+
+    let mut attempt = 0;
+
+    let result = loop {
+        match fetch(url, &mut partial, &bar).await {
+            Attempt::Done => break Ok(()),
+            Attempt::Failed { error, transient } if transient && attempt < settings.retries => {
+                let delay = backoff(attempt);
+
+                attempt += 1;
+
+                warn!(
+                    "Downloading {filename} failed, retrying in {}s ({attempt}/{}): {error}",
+                    delay.as_secs(),
+                    settings.retries
+                );
+
+                tokio::time::sleep(delay).await;
+            }
+            Attempt::Failed { error, .. } => break Err(error),
+        }
+    };
+
+    bar.finish_and_clear();
+
+    result.context(format!("Unable to download {url}"))?;
+
+    partial.file.flush().await?;
+
+    Ok((destination, format!("{:x}", partial.hasher.finalize())))
+}
+
+/// `ETag`, when it's strong, or `Last-Modified`, which identify the version of a file for `If-Range`.
+fn validator(response: &'_ reqwest::Response) -> Option<HeaderValue> {
+    let headers = response.headers();
+
+    headers
+        .get(header::ETAG)
+        .filter(|etag| !etag.as_bytes().starts_with(b"W/"))
+        .or_else(|| headers.get(header::LAST_MODIFIED))
+        .cloned()
+}
+
+/// The first byte of a `Content-Range: bytes <start>-<end>/<length>` header.
+fn range_start(response: &'_ reqwest::Response) -> Option<u64> {
+    let range = response.headers().get(header::CONTENT_RANGE)?.to_str().ok()?;
+
+    range.strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+/// Request `url`, resuming after the bytes already received in `partial`.
+async fn fetch(url: &'_ Url, partial: &'_ mut Partial, bar: &'_ indicatif::ProgressBar) -> Attempt {
+    let failed = |error: reqwest::Error| Attempt::Failed {
+        transient: is_transient(&error),
+        error: error.into(),
+    };
+
+    let restart_failed = |error: anyhow::Error| Attempt::Failed { error, transient: false };
+
+    let client = match client() {
+        Ok(client) => client,
+        Err(error) => return Attempt::Failed { error, transient: false },
+    };
+
+    // Without a validator there's no way to tell the file didn't change, so don't resume.
+    if partial.received > 0 && partial.validator.is_none() {
+        debug!("Unable to resume without an ETag or Last-Modified, restarting the download");
+
+        if let Err(error) = partial.restart().await {
+            return restart_failed(error);
+        }
+    }
+
+    let mut request = get(client, url);
+
+    if let (true, Some(validator)) = (partial.received > 0, &partial.validator) {
+        debug!("Resuming from byte {}", partial.received);
+
+        request = request
+            .header(header::RANGE, format!("bytes={}-", partial.received))
+            .header(header::IF_RANGE, validator.clone());
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(error) => return failed(error),
+    };
+
+    // The part already received is no longer available, e.g. the file shrank.
+    if partial.received > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        let received = partial.received;
+
+        if let Err(error) = partial.restart().await {
+            return restart_failed(error);
+        }
+
+        return Attempt::Failed {
+            error: anyhow::anyhow!("The server can't resume from byte {received}, restarting"),
+            transient: true,
+        };
+    }
+
+    let response = match response.error_for_status() {
+        Ok(response) => response,
+        Err(error) => return failed(error),
+    };
+
+    if partial.received > 0 {
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            // The server ignored the range, or the file changed since the first attempt, start over.
+            debug!("Not resumed, restarting the download");
+
+            if let Err(error) = partial.restart().await {
+                return restart_failed(error);
+            }
+        } else if range_start(&response) != Some(partial.received) {
+            let received = partial.received;
+
+            if let Err(error) = partial.restart().await {
+                return restart_failed(error);
+            }
+
+            return Attempt::Failed {
+                error: anyhow::anyhow!("The server returned a different range than bytes {received}-, restarting"),
+                transient: true,
+            };
+        }
+    }
+
+    if partial.received == 0 {
+        partial.validator = validator(&response);
+    }
+
+    if let Some(length) = response.content_length() {
+        bar.set_length(partial.received + length);
+    }
+
+    bar.set_position(partial.received);
+
+    let mut stream = response.bytes_stream();
+
+    while let Some(item) = stream.next().await {
+        let mut chunk = match item {
+            Ok(chunk) => chunk,
+            Err(error) => return failed(error),
+        };
+
+        partial.hasher.update(&chunk);
+        partial.received += chunk.len() as u64;
+
+        if let Err(e) = partial.file.write_all_buf(&mut chunk).await {
+            return Attempt::Failed {
+                error: e.into(),
+                transient: false,
+            };
+        }
+
+        bar.set_position(partial.received);
+    }
+
+    Attempt::Done
+}
+
+/// Fetch a small text file, such as a checksum list, with the same retries as downloads.
+pub async fn text(url: &'_ Url) -> Result<String> {
+    let settings = settings();
+    let mut attempt = 0;
+
+    loop {
+        let result = match get(client()?, url).send().await.and_then(reqwest::Response::error_for_status) {
+            Ok(response) => response.text().await,
+            Err(error) => Err(error),
+        };
+
+        match result {
+            Ok(text) => return Ok(text),
+            Err(error) if is_transient(&error) && attempt < settings.retries => {
+                let delay = backoff(attempt);
+
+                attempt += 1;
+
+                warn!(
+                    "Fetching {url} failed, retrying in {}s ({attempt}/{}): {error}",
+                    delay.as_secs(),
+                    settings.retries
+                );
+
+                tokio::time::sleep(delay).await;
+            }
+            Err(error) => return Err(error).context(format!("Unable to fetch {url}")),
+        }
+    }
+}
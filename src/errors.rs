@@ -1,4 +1,5 @@
 use crate::{
+    source::SourceKind,
     system::{OperatingSystem, PlatformArchitecture},
     version::Version,
 };
@@ -13,15 +14,39 @@ pub enum CommandError {
     #[error("Package '{name}' not found in config.")]
     PackageNotFound { name: String },
 
+    #[error("Package '{name}' isn't in released.lock for this platform, install it online and without --locked first.")]
+    NotLocked { name: String },
+
+    #[error("No previous version of '{name}' to roll back to.")]
+    NoPreviousVersion { name: String },
+
     #[error("Already up to date.")]
     NoUpdateNeeded,
 
+    #[error("{count} package update(s) available.")]
+    UpdatesAvailable { count: usize },
+
+    #[error("Failed to check {count} package(s) for updates.")]
+    CheckFailed { count: usize },
+
+    #[error("Failed to sync {count} package(s).")]
+    SyncFailed { count: usize },
+
     #[error("There aren't any packages installed!")]
     EmptyPackages,
 
     #[error("Unable to find release for {0}")]
     ReleaseNotFound(String),
 
+    #[error("Invalid version requirement '{requirement}': {msg}")]
+    InvalidVersionRequirement { requirement: String, msg: String },
+
+    #[error("No release of {name} matches the version requirement '{requirement}'")]
+    NoMatchingRelease { name: String, requirement: String },
+
+    #[error("Package '{name}' has no `lts_pattern` to select LTS releases with")]
+    MissingLtsPattern { name: String },
+
     #[error("Unable to find asset for {package}@{version} for OS: {os}; Arch: {arch}")]
     AssetNotFound {
         package: String,
@@ -30,18 +55,42 @@ pub enum CommandError {
         os: OperatingSystem,
     },
 
+    #[error("Invalid asset pattern '{pattern}': {msg}")]
+    InvalidAssetPattern { pattern: String, msg: String },
+
+    #[error("Unable to choose between the assets for {package}, set an `asset_pattern` to pick one of:\n{candidates}")]
+    AmbiguousAsset { package: String, candidates: String },
+
     #[error("Downloaded file isn't an archive or executable: '{path}': {ft}")]
     InvalidFileTypeError { path: PathBuf, ft: String },
 
+    #[error("Package '{name}' uses {source_kind} releases but no host was configured")]
+    MissingHost { name: String, source_kind: SourceKind },
+
+    #[error("Package '{name}' uses a URL template source but has no `url_template`")]
+    MissingUrlTemplate { name: String },
+
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
 
-    #[error("Unable to find binary '{binary_file_name}' in ~/.local/bin/")]
+    #[error("Unable to find binary '{binary_file_name}' in the release")]
     UnableToFindBinaryError { binary_file_name: String },
 
     #[error("Failed to download file '{asset_name}' from '{asset_uri}'")]
     AssetDownloadError { asset_uri: reqwest::Url, asset_name: String },
 
+    #[error("'{asset_name}' isn't in the download cache, unable to install it while offline")]
+    NotCached { asset_name: String },
+
+    #[error("Checksum mismatch for '{asset_name}': expected {expected}, got {actual}")]
+    ChecksumMismatch { asset_name: String, expected: String, actual: String },
+
+    #[error("Signature verification failed for '{asset_name}': {reason}")]
+    SignatureVerification { asset_name: String, reason: String },
+
+    #[error("GitHub API rate limit exceeded, rate limited until {until}. Use `released auth login` or set GITHUB_TOKEN to raise the limit.")]
+    RateLimited { until: String },
+
     #[error("Error with the GitHub API {0}")]
     GitHub(#[from] octocrab::Error),
 
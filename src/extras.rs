@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+use tracing::debug;
+use walkdir::WalkDir;
+
+use crate::config;
+
+/// Shell completions and man pages shipped inside a release archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraKind {
+    Completion,
+    ManPage,
+}
+
+#[derive(Debug, Clone)]
+pub struct Extra {
+    pub kind: ExtraKind,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+}
+
+struct Locations {
+    bash: PathBuf,
+    zsh: PathBuf,
+    fish: PathBuf,
+    man: PathBuf,
+    man_page: Regex,
+}
+
+impl Locations {
+    fn new() -> Result<Self> {
+        // Under an install root everything goes in `share`.
+        let (data_home, fish) = if let Some(root) = config::root() {
+            let share = root.join("share");
+
+            (share.clone(), share.join("fish/vendor_completions.d"))
+        } else {
+            let xdg_dir = xdg::BaseDirectories::new().context("Failed get XDG directories")?;
+            let data_home = xdg_dir.get_data_home();
+
+            // The user's own completions live in `$XDG_CONFIG_HOME/fish/completions`, leave those alone.
+            (data_home.clone(), data_home.join("fish/vendor_completions.d"))
+        };
+
+        Ok(Self {
+            bash: data_home.join("bash-completion/completions"),
+            zsh: data_home.join("zsh/site-functions"),
+            fish,
+            man: data_home.join("man"),
+            man_page: Regex::new(r"^[\w+-][\w.+-]*\.([1-8])$").expect("Unable to create regex for man pages"),
+        })
+    }
+
+    /// Where to install `file_name`, along with the command it is for.
+    fn destination(&self, file_name: &'_ str) -> Option<(ExtraKind, String, PathBuf)> {
+        if let Some(command) = file_name.strip_suffix(".bash").or_else(|| file_name.strip_suffix(".bash-completion")) {
+            Some((ExtraKind::Completion, command.to_string(), self.bash.join(command)))
+        } else if let Some(command) = file_name.strip_suffix(".zsh") {
+            Some((ExtraKind::Completion, command.to_string(), self.zsh.join(format!("_{command}"))))
+        } else if file_name.starts_with('_') && !file_name.starts_with("__") && !file_name.contains('.') {
+            Some((ExtraKind::Completion, file_name[1..].to_string(), self.zsh.join(file_name)))
+        } else if let Some(command) = file_name.strip_suffix(".fish") {
+            Some((ExtraKind::Completion, command.to_string(), self.fish.join(file_name)))
+        } else if let Some(section) = self.man_page.captures(file_name).filter(|_| !file_name.contains(".so.")) {
+            let command = file_name[..file_name.len() - 2].to_string();
+
+            Some((ExtraKind::ManPage, command, self.man.join(format!("man{}", &section[1])).join(file_name)))
+        } else {
+            None
+        }
+    }
+}
+
+// Directories which release archives conventionally keep completions in.
+const EXTRA_DIRS: [&str; 6] = ["completions", "completion", "complete", "autocomplete", "man", "doc"];
+
+fn is_extra_dir(dir: &'_ str) -> bool {
+    EXTRA_DIRS.contains(&dir)
+}
+
+/// `man`, or a section of it such as `man1`. Anything else named like a man page, e.g. `doc/tool-1.2.3`, likely isn't one.
+fn is_man_dir(dir: &'_ str) -> bool {
+    dir.strip_prefix("man")
+        .is_some_and(|section| matches!(section, "" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8"))
+}
+
+/// Find completions and man pages in an extracted release archive, along with where they should be installed.
+///
+/// Only completions in a completions or documentation directory, man pages in a `man` directory, or either named after
+/// one of the `commands`, are taken. Anything else that happens to look like one, e.g. `scripts/install.bash`, is left
+/// alone.
+pub fn find(folder: &'_ Path, commands: &'_ [String]) -> Result<Vec<Extra>> {
+    let locations = Locations::new()?;
+
+    let in_dir = |path: &Path, matches: fn(&str) -> bool| {
+        path.strip_prefix(folder)
+            .unwrap_or(path)
+            .parent()
+            .is_some_and(|parent| parent.components().any(|dir| matches(&dir.as_os_str().to_string_lossy().to_lowercase())))
+    };
+
+    Ok(WalkDir::new(folder)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let (kind, command, destination) = locations.destination(&entry.file_name().to_string_lossy())?;
+
+            let in_place = match kind {
+                ExtraKind::Completion => in_dir(entry.path(), is_extra_dir),
+                ExtraKind::ManPage => in_dir(entry.path(), is_man_dir),
+            };
+
+            if !in_place && !commands.contains(&command) {
+                debug!(
+                    "Skipping {:?}, it isn't in a completions or man directory, or named after the package",
+                    entry.path()
+                );
+                return None;
+            }
+
+            debug!("Found {kind:?} {:?}", entry.path());
+
+            Some(Extra {
+                kind,
+                source: entry.into_path(),
+                destination,
+            })
+        })
+        .collect())
+}
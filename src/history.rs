@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::{debug, warn};
+
+use crate::config::{self, InstalledPackage, PreviousVersion};
+
+/// How many previous versions of each package are kept for `rollback`.
+pub const HISTORY_LIMIT: usize = 3;
+
+fn history_home() -> Result<PathBuf> {
+    let history = config::profile_file_name("history", "");
+
+    if let Some(root) = config::root() {
+        return Ok(config::root_state_dir(root)?.join(history));
+    }
+
+    let xdg_dir = xdg::BaseDirectories::with_prefix("released").context("Failed get data directory")?;
+
+    Ok(xdg_dir.get_data_home().join(history))
+}
+
+fn saved_name(index: usize, path: &'_ Path) -> String {
+    format!("{index}-{}", path.file_name().unwrap_or_default().to_string_lossy())
+}
+
+impl PreviousVersion {
+    /// Pairs of (installed location, copy kept in the history directory).
+    pub fn saved_files(&self) -> impl Iterator<Item = (&PathBuf, PathBuf)> {
+        self.installed
+            .files()
+            .enumerate()
+            .map(|(index, path)| (path, self.directory.join(saved_name(index, path))))
+    }
+}
+
+/// Copy the files of the currently installed version of `alias` into the history directory.
+pub fn save(alias: &'_ str, installed: &'_ InstalledPackage) -> Result<PreviousVersion> {
+    let directory = history_home()?.join(alias).join(installed.version.replace('/', "_"));
+
+    if directory.exists() {
+        fs::remove_dir_all(&directory).context(format!("Unable to clear {directory:?}"))?;
+    }
+
+    fs::create_dir_all(&directory).context(format!("Unable to create {directory:?}"))?;
+
+    let previous = PreviousVersion {
+        directory,
+        installed: InstalledPackage {
+            history: Vec::new(),
+            ..installed.clone()
+        },
+    };
+
+    for (path, saved) in previous.saved_files() {
+        if !path.exists() {
+            warn!("{path:?} no longer exists, it won't be restored by a rollback");
+            continue;
+        }
+
+        debug!("Saving {path:?} to {saved:?}");
+
+        fs::copy(path, &saved).context(format!("Unable to copy {path:?} to {saved:?}"))?;
+    }
+
+    Ok(previous)
+}
+
+/// Put `previous` at the front of the history, dropping duplicates and anything over the limit.
+pub fn rotate(previous: PreviousVersion, older: Vec<PreviousVersion>) -> Vec<PreviousVersion> {
+    let version = previous.installed.version.clone();
+
+    std::iter::once(previous)
+        .chain(older.into_iter().filter(|p| p.installed.version != version))
+        .take(HISTORY_LIMIT)
+        .collect()
+}
+
+/// Delete saved versions of `alias` which are no longer referenced by `history`.
+pub fn prune(alias: &'_ str, history: &'_ [PreviousVersion]) -> Result<()> {
+    let alias_dir = history_home()?.join(alias);
+
+    if history.is_empty() && alias_dir.exists() {
+        return fs::remove_dir_all(&alias_dir).context(format!("Unable to remove {alias_dir:?}"));
+    }
+
+    let Ok(entries) = fs::read_dir(&alias_dir) else {
+        return Ok(());
+    };
+
+    let keep: HashSet<&PathBuf> = history.iter().map(|p| &p.directory).collect();
+
+    for entry in entries.filter_map(Result::ok) {
+        if !keep.contains(&entry.path()) {
+            debug!("Pruning {:?}", entry.path());
+
+            fs::remove_dir_all(entry.path()).context(format!("Unable to remove {:?}", entry.path()))?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,109 +1,273 @@
 use std::collections::HashMap;
 use std::fs;
-use std::io::Cursor;
-use std::os::unix::fs::PermissionsExt;
+use std::io::{self, Cursor, IsTerminal};
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use decompress::{decompress, ExtractOptsBuilder};
-use futures::stream::StreamExt;
 use itertools::Itertools;
-use octocrab::models::repos::{Asset, Release};
 use regex::Regex;
 use reqwest::Url;
+use semver::VersionReq;
 use skim::prelude::*;
 use strfmt::strfmt;
-use tempfile::tempdir;
-use tokio::io::AsyncWriteExt;
-use tracing::{debug, info};
+use tempfile::{tempdir, TempDir};
+use tracing::{debug, info, warn};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::{
-    config::{Config, InstalledPackage, Package},
+    cache,
+    config::{Config, InstalledPackage, LockedPackage, Package, Signature},
+    download,
     errors::CommandError,
+    extras::{self, Extra, ExtraKind},
+    history,
+    source::{self, github::GitHub, Asset, Release, ReleaseSource, Source, SourceKind},
+    spinner,
     system::System,
+    transaction::Transaction,
+    verify,
     version::{self, Version},
 };
 
-pub async fn download(url: &Url, directory: &'_ Path) -> Result<PathBuf> {
-    // TODO: Add better error handling.
+/// Look up the published SHA-256 digest for `asset`, if the release ships a checksum file covering it.
+pub async fn published_digest(release: &'_ Release, asset: &'_ Asset) -> Result<Option<String>> {
+    let Some(checksum_asset) = verify::checksum_asset(&release.assets, asset) else {
+        return Ok(None);
+    };
 
-    let filename = url
-        .path_segments()
-        .ok_or_else(|| CommandError::InvalidUrl(url.to_string()))?
-        .rev()
-        .find(|segment| !segment.is_empty())
-        .expect("No non-empty segments found in URL path");
+    debug!("Fetching checksums from {}", checksum_asset.name);
 
-    let destination = directory.join(filename);
+    let contents = download::text(&checksum_asset.browser_download_url)
+        .await
+        .context(format!("Unable to read checksum file {}", checksum_asset.name))?;
 
-    debug!("Creating destination directory {}", directory.display());
+    Ok(verify::expected_digest(&contents, &asset.name))
+}
 
-    fs::create_dir_all(directory)?;
+pub async fn release_for_repository(source: &'_ Source, package: &'_ Package, version: &'_ Version) -> Result<Release> {
+    let (owner, repo) = package.repository();
 
-    debug!("Creating destination file {}", &destination.display());
+    info!("Getting release: {} for {}/{}", version.as_tag(), owner, repo);
 
-    let mut file = tokio::fs::File::create(&destination).await?;
+    // Newest non-draft release accepted by `wanted`.
+    let channel = |releases: Vec<Release>, wanted: &dyn Fn(&Release) -> bool| -> Result<Release> {
+        releases
+            .into_iter()
+            .find(|release| !release.draft && wanted(release))
+            .ok_or_else(|| CommandError::ReleaseNotFound(format!("{}@{version}", package.name)).into())
+    };
 
-    debug!("Downloading {filename} ...");
+    match version {
+        Version::Latest => source.latest_release(owner, repo).await,
+        Version::PreRelease => channel(source.releases(owner, repo).await?, &|_| true),
+        Version::Stable => channel(source.releases(owner, repo).await?, &|release| {
+            !release.prerelease && !matches!(version::parse(&release.tag_name), Version::SemVer(v) if !v.pre.is_empty())
+        }),
+        Version::Lts => {
+            let Some(pattern) = &package.lts_pattern else {
+                return Err(CommandError::MissingLtsPattern { name: package.name.clone() }.into());
+            };
 
-    let mut stream = reqwest::get(url.clone()).await?.error_for_status()?.bytes_stream();
+            let pattern = Regex::new(pattern).context(format!("{pattern} is not a valid Regular Expression"))?;
 
-    while let Some(item) = stream.next().await {
-        file.write_all_buf(&mut item.context("Unable to retrieve next chunk from download stream..")?)
-            .await?;
+            channel(source.releases(owner, repo).await?, &|release| pattern.is_match(&release.tag_name))
+        }
+        _ => match source.release_by_tag(owner, repo, &version.as_tag()).await {
+            Ok(tagged_release) => Ok(tagged_release),
+            Err(_) => source.release_by_tag(owner, repo, &format!("v{}", version.as_tag())).await,
+        },
     }
+}
 
-    Ok(destination)
+/// The newest release whose tag satisfies `requirement`, ignoring drafts and tags that aren't SemVer.
+///
+/// The release is returned as listed, so it doesn't have to be looked up again by a tag which may or may not have a `v`.
+pub async fn matching_release(source: &'_ Source, owner: &'_ str, repo: &'_ str, requirement: &'_ VersionReq) -> Result<Option<(Version, Release)>> {
+    Ok(source
+        .releases(owner, repo)
+        .await?
+        .into_iter()
+        .filter(|release| !release.draft)
+        .filter_map(|release| match version::parse(&release.tag_name) {
+            Version::SemVer(v) if requirement.matches(&v) => Some((v, release)),
+            _ => None,
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(v, release)| (Version::SemVer(v), release)))
 }
 
-pub async fn release_for_repository(owner: &'_ str, repo: &'_ str, version: &'_ Version) -> Result<Release> {
-    info!("Getting release: {} for {}/{}", version.as_tag(), owner, repo);
+/// Resolve the version to install when none was given explicitly, honoring the package's version requirement.
+///
+/// The requirement may also name a channel: `latest`, `stable`, `pre-release` or `lts`. A requirement is matched against
+/// the listed releases, so the matching release is returned with it.
+pub async fn resolve_version(source: &'_ Source, package: &'_ Package) -> Result<(Version, Option<Release>)> {
+    let Some(requirement) = &package.version else {
+        return Ok((Version::Latest, None));
+    };
+
+    if let channel @ (Version::Latest | Version::Stable | Version::PreRelease | Version::Lts) = version::parse(requirement) {
+        return Ok((channel, None));
+    }
+
+    let parsed = version::parse_requirement(requirement).map_err(|e| CommandError::InvalidVersionRequirement {
+        requirement: requirement.clone(),
+        msg: e.to_string(),
+    })?;
 
-    let octo = octocrab::instance();
+    debug!("Resolving {} against requirement {parsed}", package.name);
 
-    if version == &Version::Latest {
-        match octo.repos(owner, repo).releases().get_latest().await {
-            Ok(latest_release) => Ok(latest_release),
-            Err(e) => Err(e.into()),
+    let (owner, repo) = package.repository();
+
+    match matching_release(source, owner, repo, &parsed).await? {
+        Some((version, release)) => Ok((version, Some(release))),
+        None => Err(CommandError::NoMatchingRelease {
+            name: package.name.clone(),
+            requirement: requirement.clone(),
         }
-    } else {
-        match octo.repos(owner, repo).releases().get_by_tag(&version.as_tag()).await {
-            Ok(tagged_release) => Ok(tagged_release),
-            Err(_) => match octo.repos(owner, repo).releases().get_by_tag(&format!("v{}", version.as_tag())).await {
-                Ok(tagged_release) => Ok(tagged_release),
-                Err(e) => Err(e.into()),
-            },
+        .into()),
+    }
+}
+
+/// Latest releases of the `packages` on github.com which follow the latest release, keyed by package name.
+///
+/// They're looked up in a few batched GraphQL queries instead of one request each, which needs a token. Packages left
+/// out, or everything when the query fails, are looked up individually as usual.
+pub async fn latest_github_releases(config: &'_ Config, packages: &'_ [&'_ Package]) -> HashMap<String, Release> {
+    let repositories: Vec<(&str, &str)> = packages
+        .iter()
+        .filter(|package| config.source_kind(package) == SourceKind::GitHub && package.host.as_deref().is_none_or(|host| host == "github.com"))
+        .filter(|package| {
+            package
+                .version
+                .as_deref()
+                .is_none_or(|requirement| version::parse(requirement) == Version::Latest)
+        })
+        .map(|package| package.repository())
+        .unique()
+        .collect();
+
+    if repositories.is_empty() {
+        return HashMap::new();
+    }
+
+    let github = match GitHub::new("github.com", config.hosts.get("github.com").cloned().unwrap_or_default()) {
+        Ok(github) if github.is_authenticated() => github,
+        _ => return HashMap::new(),
+    };
+
+    match github.latest_releases(&repositories).await {
+        Ok(releases) => {
+            debug!("Found {} of {} latest releases with GraphQL", releases.len(), repositories.len());
+
+            releases
+        }
+        Err(e) => {
+            warn!("Unable to check the latest releases in one query, checking each package instead: {e:#}");
+
+            HashMap::new()
         }
     }
 }
 
-pub async fn latest_release_tag(owner: &'_ str, repo: &'_ str) -> Option<Version> {
-    octocrab::instance()
-        .repos(owner, repo)
-        .releases()
-        .get_latest()
-        .await
-        .ok()
-        .map(|tag| version::parse(&tag.tag_name))
+/// Checksums, signatures and SBOMs published alongside the real assets.
+fn is_auxiliary(name: &'_ str) -> bool {
+    let name = name.to_lowercase();
+
+    [
+        ".sha256",
+        ".sha512",
+        ".txt",
+        ".sig",
+        ".asc",
+        ".pem",
+        ".minisig",
+        ".bundle",
+        ".sbom",
+        ".intoto.jsonl",
+    ]
+    .iter()
+    .any(|extension| name.ends_with(extension))
+        || name.contains("sha256sum")
+        || name.contains("checksums")
 }
 
-pub fn platform_asset(release: &'_ Release, system: &'_ System, user_pattern: &'_ str, _show: bool) -> Option<Asset> {
-    //
-    // First pass, remove all assets that are not for the current platform.
+/// Rank assets which match the platform equally well, higher is better.
+fn asset_score(name: &'_ str, system: &'_ System) -> i32 {
+    let name = name.to_lowercase();
+
+    let mut score = 0;
+
+    // Installer packages and debug builds are never what should be unpacked.
     #[allow(clippy::case_sensitive_file_extension_comparisons)]
-    let mut platform_assets: Vec<Asset> = release
-        .assets
+    if [".deb", ".rpm", ".msi", ".dmg", ".pkg", ".apk"]
         .iter()
-        .filter(|asset| !asset.name.ends_with(".sha256") && !asset.name.ends_with(".txt") && !asset.name.ends_with(".sig"))
-        .cloned()
-        .collect();
+        .any(|extension| name.ends_with(extension))
+        || name.contains("-debug")
+        || name.contains("_debug")
+    {
+        score -= 100;
+    }
+
+    #[allow(clippy::case_sensitive_file_extension_comparisons)]
+    let archive = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        3
+    } else if name.ends_with(".tar.xz") || name.ends_with(".tar.zst") || name.ends_with(".tar.bz2") {
+        2
+    } else {
+        i32::from(name.ends_with(".zip"))
+    };
+
+    let libc = if system.is_libc_match(&name) {
+        5
+    } else if system.libc.other().get_match_regex().is_match(&name) {
+        -5
+    } else {
+        0
+    };
+
+    score + archive + libc
+}
+
+/// Ask the user to pick one of `candidates`, `None` when the prompt is aborted.
+fn select_asset(candidates: &'_ [Asset]) -> Option<Asset> {
+    let reader = SkimItemReader::default().of_bufread(Cursor::new(candidates.iter().map(|asset| &asset.name).join("\n")));
+
+    let output = Skim::run_with(
+        &SkimOptionsBuilder::default()
+            .color(Some(crate::config::skim_colors().to_string()))
+            .height("25%".to_string())
+            .build()
+            .expect("Unable to build SkimOptionsBuilder"),
+        Some(reader),
+    )?;
+
+    if output.is_abort {
+        return None;
+    }
+
+    let selected = output.selected_items.first()?.text().to_string();
+
+    candidates.iter().find(|asset| asset.name == selected).cloned()
+}
+
+/// Find the asset of `release` for the current platform.
+///
+/// When several assets match, they are ranked by `asset_score`. The user is only asked to choose between the best ones
+/// if they tie, `interactive` is set and stdin is a terminal, otherwise the candidates are listed in the error.
+pub fn platform_asset(release: &'_ Release, system: &'_ System, package: &'_ Package, interactive: bool) -> Result<Option<Asset>> {
+    let user_pattern = &package.asset_pattern;
+
+    //
+    // First pass, remove the checksums, signatures and SBOMs.
+    let installable: Vec<Asset> = release.assets.iter().filter(|asset| !is_auxiliary(&asset.name)).cloned().collect();
+    let mut platform_assets = installable.clone();
 
     // Only one asset, such as diff-so-fancy?
     if platform_assets.len() == 1 {
         debug!("Only one asset, returning: {}", platform_assets[0].name);
 
-        return Some(platform_assets[0].clone());
+        return Ok(platform_assets.pop());
     }
 
     // Second pass - use the user provided pattern to match against the asset name if provided.
@@ -113,16 +277,18 @@ pub fn platform_asset(release: &'_ Release, system: &'_ System, user_pattern: &'
     platform_assets = if user_pattern.is_empty() {
         platform_assets.iter().filter(|asset| system.is_os_match(&asset.name)).cloned().collect()
     } else {
-        let s = HashMap::from([
-            ("os".to_string(), system.os.get_match_regex().to_string()),
-            ("arch".to_string(), system.architecture.get_match_regex().to_string()),
-        ]);
+        let s = HashMap::from([("os".to_string(), system.os.pattern()), ("arch".to_string(), system.architecture.pattern())]);
+
+        let invalid = |msg: String| CommandError::InvalidAssetPattern {
+            pattern: user_pattern.clone(),
+            msg,
+        };
 
-        let pattern = strfmt(user_pattern, &s).expect("Invalid pattern");
+        let pattern = strfmt(user_pattern, &s).map_err(|e| invalid(e.to_string()))?;
 
         debug!("Matching against pattern: {}", pattern);
 
-        let r = Regex::new(&pattern).unwrap_or_else(|_| panic!("{} is not a valid Regular Expression", &pattern));
+        let r = Regex::new(&pattern).map_err(|e| invalid(e.to_string()))?;
 
         platform_assets.iter().filter(|asset| r.is_match(&asset.name)).cloned().collect()
     };
@@ -130,50 +296,63 @@ pub fn platform_asset(release: &'_ Release, system: &'_ System, user_pattern: &'
     // TODO: Handle macOS / Universal case.
 
     if platform_assets.len() == 1 {
-        return Some(platform_assets[0].clone());
+        return Ok(platform_assets.pop());
     }
 
     // Pass through the assets again, this time matching against the architecture.
     platform_assets.retain(|asset| system.is_arch_match(&asset.name));
 
     if platform_assets.is_empty() {
-        platform_assets.clone_from(&release.assets);
-    }
-
-    match &platform_assets.len() {
-        2.. => {
-            let assets_list = platform_assets.iter().map(|a| a.name.clone()).collect_vec().join("\n");
-
-            let reader = SkimItemReader::default().of_bufread(Cursor::new(assets_list));
-
-            let selected_item: Vec<Asset> = Skim::run_with(
-                &SkimOptionsBuilder::default()
-                    .color(Some(crate::config::skim_colors().to_string()))
-                    .height("25%".to_string())
-                    .build()
-                    .expect("Unable to build SkimOptionsBuilder"),
-                Some(reader),
-            )
-            .map(|items| {
-                items
-                    .selected_items
-                    .iter()
-                    .map(|item| {
-                        platform_assets
-                            .clone()
-                            .into_iter()
-                            .find(|asset| asset.name == item.text())
-                            .expect("Unable to find selected item")
-                    })
-                    .collect()
-            })
-            .expect("Unable to run Skim");
+        platform_assets = installable;
+    }
+
+    if platform_assets.len() < 2 {
+        return Ok(platform_assets.pop());
+    }
+
+    let mut scored: Vec<(i32, Asset)> = platform_assets.into_iter().map(|asset| (asset_score(&asset.name, system), asset)).collect();
 
-            Some(selected_item[0].clone())
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.name.cmp(&b.1.name)));
+
+    for (score, asset) in &scored {
+        debug!("Candidate {} scored {score}", asset.name);
+    }
+
+    let best = scored[0].0;
+    let tied: Vec<Asset> = scored.into_iter().take_while(|(score, _)| *score == best).map(|(_, asset)| asset).collect();
+
+    if tied.len() == 1 {
+        return Ok(tied.into_iter().next());
+    }
+
+    if interactive && io::stdin().is_terminal() {
+        if let Some(asset) = spinner::suspend(|| select_asset(&tied)) {
+            return Ok(Some(asset));
         }
-        1 => Some(platform_assets[0].clone()),
-        _ => None,
     }
+
+    Err(CommandError::AmbiguousAsset {
+        package: package.name.clone(),
+        candidates: tied.iter().map(|asset| format!("  {}", asset.name)).join("\n"),
+    }
+    .into())
+}
+
+async fn verify_release_signature(release: &'_ Release, asset: &'_ Asset, signature: &'_ Signature, asset_path: &'_ Path, directory: &'_ Path) -> Result<()> {
+    let failed = |reason: String| CommandError::SignatureVerification {
+        asset_name: asset.name.clone(),
+        reason,
+    };
+
+    let Some(signature_asset) = verify::signature_asset(&release.assets, asset, signature) else {
+        return Err(failed("No signature was published for this asset".to_string()).into());
+    };
+
+    let (signature_path, _) = download::download(&signature_asset.browser_download_url, &directory.join("signatures")).await?;
+
+    verify::verify_signature(signature, asset_path, &signature_path).map_err(|e| failed(e.to_string()))?;
+
+    Ok(())
 }
 
 fn find_binary(folder: &'_ Path, bin_name: &'_ str) -> Option<DirEntry> {
@@ -183,30 +362,81 @@ fn find_binary(folder: &'_ Path, bin_name: &'_ str) -> Option<DirEntry> {
         .find(|entry| entry.file_name() == bin_name)
 }
 
-#[allow(clippy::module_name_repetitions)]
-pub async fn install_release(config: &mut Config, package: &'_ Package, system: &'_ System, version: Option<Version>, show: bool) -> Result<()> {
-    let (owner, repo) = package.name.split_once('/').expect("Invalid package name");
+/// A release that has been downloaded, verified and unpacked, ready to be installed.
+pub struct Prepared {
+    package: Package,
+    version: Version,
+    locked: LockedPackage,
+    // Pairs of (file in the download, name to install it as).
+    binaries: Vec<(PathBuf, PathBuf)>,
+    extras: Vec<Extra>,
+    // Owns the downloaded files until they are installed.
+    _temp_dir: TempDir,
+}
 
-    let bin_path = crate::config::bin_path()?;
+/// Resolve, download, verify and unpack a release without touching the installed files or state.
+///
+/// Nothing here needs the mutable `Config`, so packages can be prepared concurrently.
+pub async fn prepare_release(
+    source: &'_ Source,
+    installed: Option<&'_ InstalledPackage>,
+    package: &'_ Package,
+    system: &'_ System,
+    version: Option<Version>,
+    interactive: bool,
+) -> Result<Prepared> {
+    let (version, release) = match version {
+        Some(v) => (v, None),
+        None => resolve_version(source, package).await?,
+    };
 
-    let version = match version {
-        Some(v) => v,
-        None => match latest_release_tag(owner, repo).await {
-            Some(rel) => rel,
-            None => return Err(CommandError::ReleaseNotFound(package.name.clone()).into()),
-        },
+    // Channels are resolved to a concrete release up front, so the up to date check compares real versions.
+    let (version, release) = match version {
+        Version::Latest | Version::Stable | Version::PreRelease | Version::Lts => {
+            let release = release_for_repository(source, package, &version).await?;
+
+            (version::parse(&release.tag_name), Some(release))
+        }
+        version => (version, release),
     };
 
-    if let Some(installed) = config.installed.get(&package.alias) {
+    if let Some(installed) = installed {
         //
         if installed.version == version.as_tag() {
             return Err(CommandError::NoUpdateNeeded.into());
         }
     }
 
-    let release = release_for_repository(owner, repo, &version).await?;
+    let release = match release {
+        Some(release) => release,
+        None => release_for_repository(source, package, &version).await?,
+    };
+
+    prepare_asset(package, system, version, &release, interactive).await
+}
+
+/// Like `prepare_release` for the latest release, when it has already been fetched, e.g. by `latest_github_releases`.
+pub async fn prepare_fetched(
+    installed: Option<&'_ InstalledPackage>,
+    package: &'_ Package,
+    system: &'_ System,
+    release: &'_ Release,
+    interactive: bool,
+) -> Result<Prepared> {
+    let version = version::parse(&release.tag_name);
+
+    if installed.is_some_and(|installed| installed.version == version.as_tag()) {
+        return Err(CommandError::NoUpdateNeeded.into());
+    }
+
+    prepare_asset(package, system, version, release, interactive).await
+}
+
+/// Choose the asset of `release` for the platform, then download, verify and unpack it.
+async fn prepare_asset(package: &'_ Package, system: &'_ System, version: Version, release: &'_ Release, interactive: bool) -> Result<Prepared> {
+    let system = &system.for_package(package);
 
-    let Some(asset) = platform_asset(&release, system, &package.asset_pattern, show) else {
+    let Some(asset) = platform_asset(release, system, package, interactive)? else {
         return Err(CommandError::AssetNotFound {
             package: package.name.clone(),
             version,
@@ -216,93 +446,512 @@ pub async fn install_release(config: &mut Config, package: &'_ Package, system:
         .into());
     };
 
+    let expected_digest = published_digest(release, &asset).await?;
+
+    fetch(package, version, release, asset, &system.target(), expected_digest).await
+}
+
+/// Prepare exactly the release asset recorded in `released.lock`, failing if its SHA-256 digest doesn't match.
+pub async fn prepare_locked(source: &'_ Source, installed: Option<&'_ InstalledPackage>, package: &'_ Package, locked: &'_ LockedPackage) -> Result<Prepared> {
+    let version = version::parse(&locked.tag);
+
+    if installed.is_some_and(|installed| installed.version == version.as_tag()) {
+        return Err(CommandError::NoUpdateNeeded.into());
+    }
+
+    let asset = Asset {
+        name: locked.asset.clone(),
+        browser_download_url: Url::parse(&locked.url).map_err(|_| CommandError::InvalidUrl(locked.url.clone()))?,
+        size: locked.size,
+    };
+
+    // The release is only needed to find the signature, the asset itself comes from the lockfile.
+    let release = if package.signature.is_some() && !cache::is_offline() {
+        release_for_repository(source, package, &version).await?
+    } else {
+        Release {
+            tag_name: locked.tag.clone(),
+            ..Release::default()
+        }
+    };
+
+    fetch(package, version, &release, asset, &locked.target, Some(locked.sha256.clone())).await
+}
+
+/// Extract `asset_path` into `directory` if it's an archive, `true` when it is a standalone executable instead.
+fn unpack(asset_path: &'_ Path, directory: &'_ Path) -> Result<bool> {
+    match infer::get_from_path(asset_path) {
+        Ok(Some(ft)) if ft.matcher_type() == infer::MatcherType::Archive => {
+            decompress(asset_path, &directory.into(), &ExtractOptsBuilder::default().build()?).context("Unable to unarchive file")?;
+
+            info!("Successfully extracted '{asset_path:?}'.");
+
+            Ok(false)
+        }
+        Ok(Some(ft)) if ft.matcher_type() == infer::MatcherType::App => Ok(true),
+        Ok(Some(ft)) if ft.mime_type() == "text/x-shellscript" => Ok(true),
+        Ok(Some(ft)) => Err(CommandError::InvalidFileTypeError {
+            path: asset_path.to_path_buf(),
+            ft: ft.mime_type().to_string(),
+        }
+        .into()),
+        _ => Err(CommandError::InvalidFileTypeError {
+            path: asset_path.to_path_buf(),
+            ft: String::from("Unknown"),
+        }
+        .into()),
+    }
+}
+
+/// Find the binaries of `package` in the extracted `directory`, with the names to install them as.
+fn find_binaries(package: &'_ Package, directory: &'_ Path) -> Result<Vec<(PathBuf, PathBuf)>> {
+    if package.binaries.is_empty() {
+        let binary_file_name = if package.file_pattern.is_empty() {
+            package.alias.clone()
+        } else {
+            package.file_pattern.clone()
+        };
+
+        let Some(bin_file) = find_binary(directory, &binary_file_name) else {
+            return Err(CommandError::UnableToFindBinaryError { binary_file_name }.into());
+        };
+
+        let source = bin_file.into_path();
+        let name = PathBuf::from(source.file_name().expect("Unable to get file name"));
+
+        return Ok(vec![(source, name)]);
+    }
+
+    let mut binaries = Vec::with_capacity(package.binaries.len());
+
+    for binary in &package.binaries {
+        let Some(bin_file) = find_binary(directory, &binary.name) else {
+            return Err(CommandError::UnableToFindBinaryError {
+                binary_file_name: binary.name.clone(),
+            }
+            .into());
+        };
+
+        binaries.push((bin_file.into_path(), PathBuf::from(binary.installed_name())));
+    }
+
+    Ok(binaries)
+}
+
+/// Download, verify and unpack `asset` from `release`, the asset chosen for `target`.
+async fn fetch(
+    package: &'_ Package,
+    version: Version,
+    release: &'_ Release,
+    asset: Asset,
+    target: &'_ str,
+    expected_digest: Option<String>,
+) -> Result<Prepared> {
     let temp_dir = tempdir().context("Unable to create temporary directory")?;
     let temp_path = temp_dir.path();
 
-    if let Ok(asset_path) = download(&asset.browser_download_url, temp_path).await {
-        info!("Completed downloading {}", asset.browser_download_url);
-        info!("Path: {asset_path:?}");
+    let cached_path = temp_path.join(source::file_name(&asset.browser_download_url));
+    let cached = cache::restore(&asset.browser_download_url, expected_digest.as_deref(), &cached_path)?;
+    let from_cache = cached.is_some();
+
+    // The signature policy a cached asset was verified with, as it can't be checked again offline.
+    let mut cached_signature = None;
 
-        let mut is_standalone = false;
+    let downloaded = match cached {
+        Some(entry) => {
+            info!("Using the cached {}", asset.name);
 
-        match infer::get_from_path(&asset_path) {
-            Ok(Some(ft)) if ft.matcher_type() == infer::MatcherType::Archive => {
-                decompress(&asset_path, &temp_path.into(), &ExtractOptsBuilder::default().build()?).context("Unable to unarchive file")?;
+            cached_signature = entry.signature;
+
+            Ok((cached_path, entry.sha256))
+        }
+        None if cache::is_offline() => return Err(CommandError::NotCached { asset_name: asset.name }.into()),
+        None => {
+            let downloaded = download::download(&asset.browser_download_url, temp_path).await;
 
-                info!("Successfully extracted '{asset_path:?}'.");
+            match &downloaded {
+                Ok(_) => info!("Completed downloading {}", asset.browser_download_url),
+                Err(e) => warn!("{e:#}"),
             }
-            Ok(Some(ft)) if ft.matcher_type() == infer::MatcherType::App => is_standalone = true,
-            Ok(Some(ft)) if ft.mime_type() == "text/x-shellscript" => is_standalone = true,
-            Ok(Some(ft)) => {
-                return Err(CommandError::InvalidFileTypeError {
-                    path: asset_path,
-                    ft: ft.mime_type().to_string(),
+
+            downloaded
+        }
+    };
+
+    if let Ok((asset_path, digest)) = downloaded {
+        info!("Path: {asset_path:?}");
+
+        match expected_digest {
+            Some(expected) if expected == digest => info!("Verified SHA-256 checksum for {}", asset.name),
+            Some(expected) => {
+                return Err(CommandError::ChecksumMismatch {
+                    asset_name: asset.name,
+                    expected,
+                    actual: digest,
                 }
                 .into())
             }
-            _ => {
-                return Err(CommandError::InvalidFileTypeError {
-                    path: asset_path,
-                    ft: String::from("Unknown"),
+            None => warn!("No published checksum found for {}, skipping verification", asset.name),
+        }
+
+        // Signatures are published with the release, which can't be fetched while offline.
+        if let Some(signature) = &package.signature {
+            if from_cache && cache::is_offline() {
+                if cached_signature.as_ref() != Some(signature) {
+                    return Err(CommandError::SignatureVerification {
+                        asset_name: asset.name,
+                        reason: "The cached asset wasn't verified with this package's signature policy, install it online first".to_string(),
+                    }
+                    .into());
                 }
-                .into())
+
+                info!("Using the signature verification of the cached {}", asset.name);
+            } else {
+                verify_release_signature(release, &asset, signature, &asset_path, temp_path).await?;
+
+                info!("Verified signature for {}", asset.name);
             }
         }
 
-        let binary_file_name = if is_standalone {
-            asset_path.to_string_lossy().to_string()
-        } else if package.file_pattern.is_empty() {
-            package.alias.clone()
-        } else {
-            package.file_pattern.clone()
+        // Only verified downloads are cached, along with the signature policy they passed.
+        if !(from_cache && cache::is_offline()) {
+            if let Err(e) = cache::store(&asset.browser_download_url, &asset_path, &digest, package.signature.as_ref()) {
+                warn!("Unable to cache {}: {e}", asset.name);
+            }
+        }
+
+        let locked = LockedPackage {
+            name: package.name.clone(),
+            target: target.to_string(),
+            tag: release.tag_name.clone(),
+            asset: asset.name.clone(),
+            url: asset.browser_download_url.to_string(),
+            size: asset.size,
+            sha256: digest,
         };
 
-        let source = if is_standalone {
-            asset_path
+        let is_standalone = unpack(&asset_path, temp_path)?;
+
+        let binaries = if is_standalone {
+            vec![(asset_path, PathBuf::from(&package.alias))]
         } else {
-            match find_binary(temp_path, &binary_file_name) {
-                Some(bin_file) => bin_file.into_path(),
-                None => return Err(CommandError::UnableToFindBinaryError { binary_file_name }.into()),
-            }
+            find_binaries(package, temp_path)?
         };
 
-        let destination = if is_standalone {
-            bin_path.join(&package.alias)
+        let extras = if is_standalone {
+            Vec::new()
         } else {
-            bin_path.join(source.file_name().expect("Unable to get file name"))
+            let commands: Vec<String> = std::iter::once(package.alias.clone())
+                .chain(binaries.iter().map(|(_, name)| name.to_string_lossy().to_string()))
+                .collect();
+
+            extras::find(temp_path, &commands)?
+        };
+
+        Ok(Prepared {
+            package: package.clone(),
+            version,
+            locked,
+            binaries,
+            extras,
+            _temp_dir: temp_dir,
+        })
+    } else {
+        drop(temp_dir);
+
+        Err(CommandError::AssetDownloadError {
+            asset_uri: asset.browser_download_url,
+            asset_name: asset.name,
+        }
+        .into())
+    }
+}
+
+impl Prepared {
+    /// The release version which is about to be installed.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Atomically install the prepared files and record them in the state file.
+    pub fn install(self, config: &mut Config) -> Result<()> {
+        // Keep a copy of the version being replaced, so it can be restored with `rollback`. Reinstalling the same
+        // version, e.g. to repair missing files, replaces nothing worth going back to.
+        let (history, previous_files): (_, Vec<PathBuf>) = match config.installed.get(&self.package.alias) {
+            Some(current) if current.version == self.version.as_tag() => (current.history.clone(), current.files().cloned().collect()),
+            Some(current) => (
+                history::rotate(history::save(&self.package.alias, current)?, current.history.clone()),
+                current.files().cloned().collect(),
+            ),
+            None => (Vec::new(), Vec::new()),
         };
 
-        info!("Binary '{source:?}'.");
-        info!("Renaming to '{destination:?}' and setting executable.");
+        let bin_path = config.bin_dir(Some(&self.package))?;
+
+        fs::create_dir_all(&bin_path).context(format!("Unable to create {bin_path:?}"))?;
+
+        let binaries: Vec<(&PathBuf, PathBuf)> = self.binaries.iter().map(|(source, name)| (source, bin_path.join(name))).collect();
+
+        // Any error before the transaction is committed restores the previously installed files.
+        let mut transaction = Transaction::default();
+
+        for (source, destination) in &binaries {
+            info!("Binary '{source:?}'.");
+            info!("Renaming to '{destination:?}' and setting executable.");
 
-        fs::copy(&source, &destination).context(format!("Unable to copy {source:?} to {destination:?}"))?;
-        fs::set_permissions(&destination, fs::Permissions::from_mode(0o755))?;
+            transaction.install(source, destination, 0o755)?;
+        }
+
+        // Never replace another package's completions or man pages.
+        let extras: Vec<&Extra> = self
+            .extras
+            .iter()
+            .filter(|extra| {
+                let owner = config
+                    .installed
+                    .iter()
+                    .find(|(alias, installed)| **alias != self.package.alias && installed.files().any(|file| *file == extra.destination));
+
+                if let Some((owner, _)) = owner {
+                    warn!("Not installing {:?}, it belongs to {owner}", extra.destination);
+                }
+
+                owner.is_none()
+            })
+            .collect();
+
+        for extra in &extras {
+            info!("Installing {:?} to '{:?}'.", extra.kind, extra.destination);
 
-        if !config.installed.contains_key(&package.alias) {
-            config.packages.insert(package.name.clone(), package.clone());
+            if let Some(parent) = extra.destination.parent() {
+                fs::create_dir_all(parent).context(format!("Unable to create {parent:?}"))?;
+            }
+
+            transaction.install(&extra.source, &extra.destination, 0o644)?;
+        }
+
+        let extras_of = |kind: ExtraKind| extras.iter().filter(|e| e.kind == kind).map(|e| e.destination.clone()).collect();
+
+        let mut destinations = binaries.into_iter().map(|(_, destination)| destination);
+
+        // Only take on the new state once it's saved, the config may be shared with other installs.
+        let mut updated = config.clone();
+
+        if !updated.installed.contains_key(&self.package.alias) {
+            updated.packages_mut().insert(self.package.name.clone(), self.package.clone());
         }
 
-        config.installed.insert(
-            package.alias.clone(),
+        updated.installed.insert(
+            self.package.alias.clone(),
             InstalledPackage {
-                name: package.name.clone(),
-                version: version.as_tag().clone(),
-                path: destination.clone(),
+                name: self.package.name.clone(),
+                version: self.version.as_tag().clone(),
+                path: destinations.next().expect("At least one binary is installed"),
+                binaries: destinations.collect(),
+                completions: extras_of(ExtraKind::Completion),
+                man_pages: extras_of(ExtraKind::ManPage),
+                history,
+                locked: Some(self.locked.clone()),
             },
         );
 
-        config.save()?;
+        updated.lock(&self.package.alias, self.locked);
 
-        drop(temp_dir);
+        transaction.check_interrupted()?;
+
+        updated.save()?;
+
+        *config = updated;
+
+        transaction.commit();
+
+        let installed = &config.installed[&self.package.alias];
+
+        // Remove anything that only shipped with the version that was replaced.
+        for path in previous_files
+            .iter()
+            .filter(|path| !installed.files().any(|file| file == *path) && path.exists())
+        {
+            debug!("Removing stale file {path:?}");
+
+            fs::remove_file(path).context(format!("Unable to remove {path:?}"))?;
+        }
+
+        history::prune(&self.package.alias, &installed.history)?;
 
         Ok(())
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub async fn install_release(config: &mut Config, package: &'_ Package, system: &'_ System, version: Option<Version>, interactive: bool) -> Result<()> {
+    let source = Source::for_package(config, package, system)?;
+
+    // Without the network, only what's in the lockfile can be installed from the cache.
+    let prepared = if cache::is_offline() {
+        let Some(locked) = config.lock_for(&package.alias, &system.for_package(package).target()) else {
+            return Err(CommandError::NotLocked { name: package.alias.clone() }.into());
+        };
+
+        prepare_locked(&source, config.installed.get(&package.alias), package, locked).await?
     } else {
-        drop(temp_dir);
+        prepare_release(&source, config.installed.get(&package.alias), package, system, version, interactive).await?
+    };
 
-        Err(CommandError::AssetDownloadError {
-            asset_uri: asset.browser_download_url,
-            asset_name: asset.name,
+    prepared.install(config)
+}
+
+/// Delete the installed files of `alias` along with its saved history, and forget about it.
+///
+/// The state file isn't saved, that's left to the caller.
+pub fn uninstall(config: &mut Config, alias: &'_ str) -> Result<()> {
+    let Some(installed) = config.installed.get(alias) else {
+        return Ok(());
+    };
+
+    for path in installed.files().filter(|path| path.exists()) {
+        debug!("Removing {:?}", path);
+
+        if fs::remove_file(path).is_err() {
+            return Err(CommandError::FileDelete { file_name: path.clone() }.into());
+        };
+    }
+
+    config.installed.remove(alias);
+    config.locked.remove(alias);
+
+    history::prune(alias, &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::{Libc, OperatingSystem, PlatformArchitecture};
+
+    fn linux_x86_64() -> System {
+        System {
+            architecture: PlatformArchitecture::Amd64,
+            os: OperatingSystem::Linux,
+            libc: Libc::Gnu,
         }
-        .into())
+    }
+
+    fn release(names: &[&str]) -> Release {
+        Release {
+            tag_name: "v1.0.0".to_string(),
+            assets: names
+                .iter()
+                .map(|name| Asset {
+                    name: (*name).to_string(),
+                    browser_download_url: Url::parse(&format!("https://example.com/{name}")).unwrap(),
+                    size: 0,
+                })
+                .collect(),
+            ..Release::default()
+        }
+    }
+
+    fn package() -> Package {
+        Package {
+            name: "owner/tool".to_string(),
+            alias: "tool".to_string(),
+            ..Package::default()
+        }
+    }
+
+    fn chosen(names: &[&str]) -> Option<String> {
+        platform_asset(&release(names), &linux_x86_64(), &package(), false)
+            .unwrap()
+            .map(|asset| asset.name)
+    }
+
+    #[test]
+    fn auxiliary_assets() {
+        for name in [
+            "tool-linux-x86_64.tar.gz.sha256",
+            "tool-linux-x86_64.tar.gz.sig",
+            "tool-linux-x86_64.tar.gz.asc",
+            "tool-linux-x86_64.tar.gz.pem",
+            "tool-linux-x86_64.tar.gz.minisig",
+            "tool-linux-x86_64.tar.gz.bundle",
+            "tool-linux-x86_64.sbom",
+            "tool-linux-x86_64.intoto.jsonl",
+            "checksums.txt",
+            "SHA256SUMS",
+            "tool_1.0.0_checksums.txt",
+        ] {
+            assert!(is_auxiliary(name), "{name}");
+        }
+
+        assert!(!is_auxiliary("tool-linux-x86_64.tar.gz"));
+        assert!(!is_auxiliary("tool-linux-x86_64"));
+    }
+
+    #[test]
+    fn scores_archives_and_libc() {
+        let system = linux_x86_64();
+
+        assert!(asset_score("tool-linux-x86_64.tar.gz", &system) > asset_score("tool-linux-x86_64.tar.xz", &system));
+        assert!(asset_score("tool-linux-x86_64.tar.xz", &system) > asset_score("tool-linux-x86_64.zip", &system));
+        assert!(asset_score("tool-linux-x86_64.zip", &system) > asset_score("tool-linux-x86_64.deb", &system));
+        assert!(asset_score("tool-linux-x86_64-gnu.tar.gz", &system) > asset_score("tool-linux-x86_64-musl.tar.gz", &system));
+        assert!(asset_score("tool-linux-x86_64.tar.gz", &system) > asset_score("tool-linux-x86_64-debug.tar.gz", &system));
+    }
+
+    #[test]
+    fn best_score_wins() {
+        assert_eq!(
+            chosen(&["tool-linux-x86_64.deb", "tool-linux-x86_64.zip", "tool-linux-x86_64.tar.gz"]).as_deref(),
+            Some("tool-linux-x86_64.tar.gz")
+        );
+        assert_eq!(
+            chosen(&["tool-x86_64-unknown-linux-musl.tar.gz", "tool-x86_64-unknown-linux-gnu.tar.gz"]).as_deref(),
+            Some("tool-x86_64-unknown-linux-gnu.tar.gz")
+        );
+    }
+
+    #[test]
+    fn ties_are_ambiguous() {
+        let error = platform_asset(
+            &release(&["tool-linux-x86_64-a.tar.gz", "tool-linux-x86_64-b.tar.gz"]),
+            &linux_x86_64(),
+            &package(),
+            false,
+        )
+        .expect_err("tied assets should not be chosen without asking");
+
+        let message = error.to_string();
+
+        assert!(message.contains("tool-linux-x86_64-a.tar.gz"), "{message}");
+        assert!(message.contains("tool-linux-x86_64-b.tar.gz"), "{message}");
+    }
+
+    #[test]
+    fn never_chooses_auxiliary_assets() {
+        assert_eq!(
+            chosen(&[
+                "checksums.txt",
+                "tool-linux-x86_64.tar.gz.sha256",
+                "tool-linux-x86_64.tar.gz.sig",
+                "tool-linux-x86_64.sbom",
+                "tool-linux-x86_64.tar.gz",
+            ])
+            .as_deref(),
+            Some("tool-linux-x86_64.tar.gz")
+        );
+
+        // The only installable asset, even though the checksum files also match the platform.
+        assert_eq!(
+            chosen(&["tool", "tool-linux-x86_64.sha256", "tool-linux-x86_64.tar.gz.sig"]).as_deref(),
+            Some("tool")
+        );
+
+        // Nothing matches the platform, falling back to every asset must still leave the auxiliary ones out.
+        assert_eq!(
+            chosen(&["tool-darwin-arm64.zip", "tool.tar.gz", "checksums.txt", "tool.tar.gz.sig"]).as_deref(),
+            Some("tool.tar.gz")
+        );
+
+        assert_eq!(chosen(&["checksums.txt", "tool.sig"]), None);
     }
 }
@@ -1,30 +1,47 @@
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::Result;
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell as CompletionShell};
 use shadow_rs::shadow;
-use tracing::info;
 use tracing_subscriber::{filter::filter_fn, prelude::*};
 
 // TODO: Replace with vergen-gitcl
 // https://crates.io/crates/shadow-rs
 shadow!(build);
 
+mod auth;
+mod cache;
 mod cli;
 mod cmd;
 mod config;
+mod download;
 mod errors;
+mod extras;
+mod history;
 mod install;
+mod output;
+mod source;
 mod spinner;
 mod system;
+mod transaction;
+mod verify;
 mod version;
 
 use self::cli::RunCommand;
 use self::cmd::add::Add;
+use self::cmd::auth::Auth;
+use self::cmd::cache::Cache;
 use self::cmd::list::List;
+use self::cmd::outdated::Outdated;
 use self::cmd::remove::Remove;
+use self::cmd::rollback::Rollback;
+use self::cmd::sync::Sync;
 use self::cmd::update::Update;
+use self::errors::CommandError;
+use self::output::OutputFormat;
 
 #[derive(Debug, Clone, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -41,6 +58,34 @@ pub struct CLI {
     #[clap(flatten)]
     verbose: clap_verbosity_flag::Verbosity,
 
+    /// Output format for results and errors.
+    #[arg(long, global = true, value_enum, default_value_t)]
+    format: OutputFormat,
+
+    /// Profile of packages to use, defaults to `RELEASED_PROFILE` or the top level packages.
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    /// Install into DIR/bin and DIR/share instead, keeping the config and state in DIR/.released.
+    #[arg(long, global = true, value_name = "DIR")]
+    root: Option<PathBuf>,
+
+    /// Only install from the download cache, using the versions in `released.lock`.
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// How long a download may go without receiving data before it is retried, e.g. `30s` or `2m`.
+    #[arg(long, global = true, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "30s")]
+    timeout: Duration,
+
+    /// How long to wait for a connection to be established.
+    #[arg(long, global = true, value_name = "DURATION", value_parser = humantime::parse_duration, default_value = "10s")]
+    connect_timeout: Duration,
+
+    /// How many times to retry a failed download, resuming where it left off.
+    #[arg(long, global = true, value_name = "COUNT", default_value_t = 5)]
+    retries: u32,
+
     /// Clap subcommand to run.
     #[clap(subcommand)]
     command: Commands,
@@ -59,6 +104,18 @@ pub enum Commands {
     /// Update packages to the latest version available from GitHub.
     #[clap(alias = "up")]
     Update(Update),
+    /// Show packages with updates available, without installing them.
+    ///
+    /// Exits with a nonzero status when any updates are available.
+    Outdated(Outdated),
+    /// Restore a previously installed version of a package.
+    Rollback(Rollback),
+    /// Install, reinstall or remove packages until the installed ones match the config.
+    Sync(Sync),
+    /// Manage the download cache of release assets.
+    Cache(Cache),
+    /// Save, remove or show the tokens used to access forges.
+    Auth(Auth),
     /// Generate shell completions to stdout.
     Completions {
         #[clap(value_enum)]
@@ -69,32 +126,54 @@ pub enum Commands {
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    ctrlc::set_handler(|| {
-        std::process::exit(0);
-    })
-    .expect("Error setting Ctrl-C handler");
+    ctrlc::set_handler(transaction::interrupt).expect("Error setting Ctrl-C handler");
 
     let cli = CLI::parse();
 
-    // Log from this crate only.
+    output::set_format(cli.format);
+    config::set_root(cli.root);
+    config::set_profile(cli.profile);
+    cache::set_offline(cli.offline);
+    download::configure(download::Settings {
+        connect_timeout: cli.connect_timeout,
+        read_timeout: cli.timeout,
+        retries: cli.retries,
+    });
+
+    // Log from this crate only, to stderr so it doesn't mix with structured output.
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(cli.verbose.log_level_filter().to_string()))
-        .with(tracing_subscriber::fmt::layer().with_filter(filter_fn(|metadata| metadata.target().starts_with(env!("CARGO_PKG_NAME")))))
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(io::stderr)
+                .with_filter(filter_fn(|metadata| metadata.target().starts_with(env!("CARGO_PKG_NAME")))),
+        )
         .init();
 
-    if let Ok(env_api_token) = std::env::var("GITHUB_TOKEN") {
-        info!("Initializing the GitHub client with token from environment");
-        octocrab::initialise(octocrab::Octocrab::builder().personal_token(env_api_token).build()?);
-    };
-
-    match cli.command {
-        Commands::Add(add) => add.run().await?,
-        Commands::Remove(remove) => remove.run().await?,
-        Commands::List(list) => list.run().await?,
-        Commands::Update(update) => update.run().await?,
+    let result = match cli.command {
+        Commands::Add(add) => add.run().await,
+        Commands::Remove(remove) => remove.run().await,
+        Commands::List(list) => list.run().await,
+        Commands::Update(update) => update.run().await,
+        Commands::Outdated(outdated) => outdated.run().await,
+        Commands::Rollback(rollback) => rollback.run().await,
+        Commands::Sync(sync) => sync.run().await,
+        Commands::Cache(cache) => cache.run().await,
+        Commands::Auth(auth) => auth.run().await,
 
-        Commands::Completions { shell } => generate(shell, &mut CLI::command(), "released", &mut io::stdout().lock()),
+        Commands::Completions { shell } => {
+            generate(shell, &mut CLI::command(), "released", &mut io::stdout().lock());
+            Ok(())
+        }
     };
 
-    Ok(())
+    match result {
+        // Available updates were already reported, the exit status is what matters.
+        Err(CommandError::UpdatesAvailable { .. }) if output::is_structured() => std::process::exit(1),
+        Err(e) if output::is_structured() => {
+            output::print_error(&e)?;
+            std::process::exit(1);
+        }
+        result => Ok(result?),
+    }
 }
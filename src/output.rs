@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable tables and progress spinners.
+    #[default]
+    Table,
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    Yaml,
+    Toml,
+}
+
+pub fn set_format(format: OutputFormat) {
+    FORMAT.get_or_init(|| format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Whether output should be machine readable, in which case progress output is suppressed.
+pub fn is_structured() -> bool {
+    format() != OutputFormat::Table
+}
+
+/// Print a single record, e.g. an error or the result of one package.
+pub fn print_one<T: Serialize>(item: &'_ T) -> Result<()> {
+    let output = match format() {
+        OutputFormat::Table | OutputFormat::Ndjson => serde_json::to_string(item)?,
+        OutputFormat::Json => serde_json::to_string_pretty(item)?,
+        OutputFormat::Yaml => serde_yaml_ng::to_string(item)?,
+        OutputFormat::Toml => toml::to_string(item).context("Serializing output into TOML format")?,
+    };
+
+    println!("{}", output.trim_end());
+
+    Ok(())
+}
+
+/// Print a list of records, stored under `key` for formats which need a top level table.
+pub fn print<T: Serialize>(key: &'_ str, items: &'_ [T]) -> Result<()> {
+    match format() {
+        OutputFormat::Table | OutputFormat::Ndjson => {
+            for item in items {
+                println!("{}", serde_json::to_string(item)?);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml_ng::to_string(items)?),
+        OutputFormat::Toml => print!(
+            "{}",
+            toml::to_string(&BTreeMap::from([(key, items)])).context("Serializing output into TOML format")?
+        ),
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: String,
+}
+
+pub fn print_error(error: &'_ impl std::fmt::Display) -> Result<()> {
+    print_one(&ErrorOutput { error: error.to_string() })
+}
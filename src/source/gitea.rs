@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+    Client, Url,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{auth, config::Host, download};
+
+use super::{encode_segment, http_client, Asset, Release, ReleaseSource, SourceKind};
+
+/// Gitea and its Forgejo fork share the same release API.
+pub struct Gitea {
+    client: Client,
+    api_url: String,
+}
+
+#[derive(Deserialize)]
+struct GiteaRelease {
+    tag_name: String,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    published_at: Option<String>,
+    html_url: Option<String>,
+    #[serde(default)]
+    assets: Vec<GiteaAsset>,
+}
+
+#[derive(Deserialize)]
+struct GiteaAsset {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    browser_download_url: Url,
+}
+
+impl From<GiteaRelease> for Release {
+    fn from(release: GiteaRelease) -> Self {
+        Self {
+            tag_name: release.tag_name,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            published_at: release.published_at,
+            html_url: release.html_url,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| Asset {
+                    name: asset.name,
+                    browser_download_url: asset.browser_download_url,
+                    size: asset.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Gitea {
+    pub fn new(host: &'_ str, settings: Host) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+
+        if let Some(token) = settings.token.or_else(|| auth::token(host, auth::variables(SourceKind::Gitea, host))) {
+            let authorization = HeaderValue::from_str(&format!("token {token}")).context(format!("Invalid token for {host}"))?;
+
+            // Attachments of private repositories are downloaded from the same host.
+            download::authorize(host, authorization.clone());
+
+            headers.insert(AUTHORIZATION, authorization);
+        }
+
+        Ok(Self {
+            client: http_client(headers)?,
+            api_url: settings.api_url.unwrap_or_else(|| format!("https://{host}/api/v1")),
+        })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, owner: &'_ str, repo: &'_ str, path: &'_ str) -> Result<T> {
+        let url = format!("{}/repos/{owner}/{repo}/{path}", self.api_url);
+
+        debug!("Gitea GET {url}");
+
+        Ok(self.client.get(&url).send().await?.error_for_status()?.json().await?)
+    }
+}
+
+impl ReleaseSource for Gitea {
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>> {
+        let releases: Vec<GiteaRelease> = self.get(owner, repo, "releases?limit=50").await?;
+
+        Ok(releases.into_iter().map(Release::from).collect())
+    }
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release> {
+        let release: GiteaRelease = self.get(owner, repo, "releases/latest").await?;
+
+        Ok(release.into())
+    }
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release> {
+        let release: GiteaRelease = self.get(owner, repo, &format!("releases/tags/{}", encode_segment(tag))).await?;
+
+        Ok(release.into())
+    }
+}
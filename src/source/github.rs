@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use octocrab::{models::repos, Octocrab};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ETAG, IF_NONE_MATCH},
+    StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+use crate::{auth, cache, config::Host, download, errors::CommandError};
+
+use super::{Asset, Release, ReleaseSource, SourceKind};
+
+// The shared github.com client, and whether it has a token, which the GraphQL API requires.
+static GITHUB_COM: OnceLock<(Arc<Octocrab>, bool)> = OnceLock::new();
+
+// How many repositories to look up in one GraphQL query.
+const GRAPHQL_BATCH_SIZE: usize = 50;
+
+// The last rate limit reported by each host.
+static RATE_LIMITS: Mutex<Option<HashMap<String, RateLimit>>> = Mutex::new(None);
+
+// Rather than failing, wait for the rate limit to reset when it's this close.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+pub struct GitHub {
+    client: Arc<Octocrab>,
+    host: String,
+    authenticated: bool,
+}
+
+/// The shared github.com client, built when it's first needed so commands which don't talk to GitHub never look up a token.
+fn github_com() -> Result<(Arc<Octocrab>, bool)> {
+    if let Some(client) = GITHUB_COM.get() {
+        return Ok(client.clone());
+    }
+
+    let client = match auth::token("github.com", auth::variables(SourceKind::GitHub, "github.com")) {
+        Some(token) => {
+            info!("Initializing the GitHub client with a token");
+
+            authorize_downloads("github.com", &token)?;
+
+            (Arc::new(Octocrab::builder().personal_token(token).build()?), true)
+        }
+        None => (octocrab::instance(), false),
+    };
+
+    Ok(GITHUB_COM.get_or_init(|| client).clone())
+}
+
+/// Download release assets from `host` with `token` too, they're private when the repository is.
+fn authorize_downloads(host: &'_ str, token: &'_ str) -> Result<()> {
+    download::authorize(
+        host,
+        HeaderValue::from_str(&format!("Bearer {token}")).context(format!("Invalid token for {host}"))?,
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    limit: u64,
+    remaining: u64,
+    /// Seconds since the epoch when the quota is replenished.
+    reset: u64,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &'_ HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name)?.to_str().ok()?.parse().ok();
+
+        Some(Self {
+            limit: header("x-ratelimit-limit")?,
+            remaining: header("x-ratelimit-remaining")?,
+            reset: header("x-ratelimit-reset")?,
+        })
+    }
+
+    fn reset_at(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.reset)
+    }
+
+    /// Local time of the reset, as HH:MM.
+    fn until(self) -> String {
+        DateTime::<Local>::from(self.reset_at()).format("%H:%M").to_string()
+    }
+}
+
+/// A response body kept with its `ETag`, so it can be requested again with `If-None-Match`.
+///
+/// GitHub doesn't count conditional requests answered with `304 Not Modified` against the rate limit.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    body: String,
+}
+
+impl GitHub {
+    /// github.com without a configured token shares the global client, anything else (e.g. GitHub Enterprise Server) gets its own.
+    pub fn new(host: &'_ str, settings: Host) -> Result<Self> {
+        if host == "github.com" && settings.api_url.is_none() && settings.token.is_none() {
+            let (client, authenticated) = github_com()?;
+
+            return Ok(Self {
+                client,
+                host: host.to_string(),
+                authenticated,
+            });
+        }
+
+        let api_url = settings.api_url.unwrap_or_else(|| format!("https://{host}/api/v3"));
+
+        debug!("Creating GitHub client for {host} using {api_url}");
+
+        let mut builder = Octocrab::builder().base_uri(api_url.as_str()).context(format!("Invalid API URL: {api_url}"))?;
+        let token = settings.token.or_else(|| auth::token(host, auth::variables(SourceKind::GitHub, host)));
+        let authenticated = token.is_some();
+
+        if let Some(token) = token {
+            authorize_downloads(host, &token)?;
+
+            builder = builder.personal_token(token);
+        }
+
+        Ok(Self {
+            client: Arc::new(builder.build()?),
+            host: host.to_string(),
+            authenticated,
+        })
+    }
+
+    /// Whether requests are made with a token, and so can use the GraphQL API.
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated
+    }
+
+    /// The latest release of each `owner/repo`, looked up in batches with the GraphQL API.
+    ///
+    /// Repositories without a release, or whose release has more assets than fit in one query, are left out.
+    pub async fn latest_releases(&self, repositories: &'_ [(&'_ str, &'_ str)]) -> Result<HashMap<String, Release>> {
+        let mut releases = HashMap::with_capacity(repositories.len());
+
+        for batch in repositories.chunks(GRAPHQL_BATCH_SIZE) {
+            // Each repository is aliased `r<index>`, with its owner and name passed as variables.
+            let mut variables = serde_json::Map::new();
+            let mut parameters = Vec::with_capacity(batch.len());
+            let mut fields = Vec::with_capacity(batch.len());
+
+            for (index, (owner, repo)) in batch.iter().enumerate() {
+                variables.insert(format!("o{index}"), (*owner).into());
+                variables.insert(format!("n{index}"), (*repo).into());
+                parameters.push(format!("$o{index}: String!, $n{index}: String!"));
+                fields.push(format!("r{index}: repository(owner: $o{index}, name: $n{index}) {{ ...latest }}"));
+            }
+
+            let query = format!(
+                "query({}) {{ {} }}
+                fragment latest on Repository {{
+                    latestRelease {{
+                        tagName isPrerelease isDraft publishedAt url
+                        releaseAssets(first: 100) {{ totalCount nodes {{ name downloadUrl size }} }}
+                    }}
+                }}",
+                parameters.join(", "),
+                fields.join(" ")
+            );
+
+            debug!("GitHub GraphQL query for {} repositories", batch.len());
+
+            let response: GraphQlResponse = self
+                .client
+                .graphql(&serde_json::json!({ "query": query, "variables": variables }))
+                .await
+                .context("GitHub GraphQL query failed")?;
+
+            // Missing repositories are reported here, they are looked up again individually.
+            for error in &response.errors {
+                debug!("GitHub GraphQL: {}", error.message);
+            }
+
+            for (index, (owner, repo)) in batch.iter().enumerate() {
+                let release = response
+                    .data
+                    .as_ref()
+                    .and_then(|data| data.get(&format!("r{index}")))
+                    .and_then(|repository| repository.as_ref()?.latest_release.clone());
+
+                match release {
+                    Some(release) if release.release_assets.total_count <= release.release_assets.nodes.len() => {
+                        releases.insert(format!("{owner}/{repo}"), release.into());
+                    }
+                    Some(_) => debug!("{owner}/{repo} has too many assets for one query"),
+                    None => debug!("No latest release of {owner}/{repo} found"),
+                }
+            }
+        }
+
+        Ok(releases)
+    }
+
+    fn cache_path(&self, route: &'_ str) -> Result<PathBuf> {
+        let key = format!("{:x}", Sha256::digest(format!("{}{route}", self.host)));
+
+        Ok(cache::responses_dir()?.join(format!("{key}.json")))
+    }
+
+    fn cached(&self, route: &'_ str) -> Option<CachedResponse> {
+        let path = self.cache_path(route).ok()?;
+
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    fn store(&self, route: &'_ str, cached: &'_ CachedResponse) -> Result<()> {
+        let path = self.cache_path(route)?;
+        let parent = path.parent().expect("Cached responses have a parent directory");
+
+        fs::create_dir_all(parent).context(format!("Unable to create {parent:?}"))?;
+
+        fs::write(&path, serde_json::to_string(cached)?).context(format!("Unable to write {path:?}"))
+    }
+
+    /// Wait for, or fail on, an exhausted rate limit before making another request.
+    async fn check_rate_limit(&self) -> Result<()> {
+        let limited = RATE_LIMITS
+            .lock()
+            .expect("Rate limit lock poisoned")
+            .as_ref()
+            .and_then(|limits| limits.get(&self.host).copied())
+            .filter(|limit| limit.remaining == 0);
+
+        let Some(limit) = limited else {
+            return Ok(());
+        };
+
+        match limit.reset_at().duration_since(SystemTime::now()) {
+            // Already reset.
+            Err(_) => Ok(()),
+            Ok(wait) if wait <= MAX_RATE_LIMIT_WAIT => {
+                warn!("GitHub API rate limit exceeded, waiting {}s for it to reset", wait.as_secs() + 1);
+
+                tokio::time::sleep(wait + Duration::from_secs(1)).await;
+
+                Ok(())
+            }
+            Ok(_) => Err(CommandError::RateLimited { until: limit.until() }.into()),
+        }
+    }
+
+    fn record_rate_limit(&self, headers: &'_ HeaderMap) -> Option<RateLimit> {
+        let limit = RateLimit::from_headers(headers)?;
+
+        info!(
+            "GitHub API: {} of {} requests remaining, resets at {}",
+            limit.remaining,
+            limit.limit,
+            limit.until()
+        );
+
+        RATE_LIMITS
+            .lock()
+            .expect("Rate limit lock poisoned")
+            .get_or_insert_with(HashMap::new)
+            .insert(self.host.clone(), limit);
+
+        Some(limit)
+    }
+
+    /// GET `route` from the REST API, revalidating a previous response with its `ETag`.
+    async fn get<T: DeserializeOwned>(&self, route: &'_ str) -> Result<T> {
+        let cached = self.cached(route);
+
+        let mut attempts = 0;
+
+        let (status, headers, body) = loop {
+            self.check_rate_limit().await?;
+
+            let mut headers = HeaderMap::new();
+
+            if let Some(cached) = &cached {
+                if let Ok(etag) = HeaderValue::from_str(&cached.etag) {
+                    headers.insert(IF_NONE_MATCH, etag);
+                }
+            }
+
+            debug!("GitHub GET {route}");
+
+            let response = self.client._get_with_headers(route, Some(headers)).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let limit = self.record_rate_limit(&headers);
+            let body = self.client.body_to_string(response).await?;
+
+            let limited = matches!(status, StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS) && limit.is_some_and(|limit| limit.remaining == 0);
+
+            attempts += 1;
+
+            // Try once more, `check_rate_limit` waits if the reset is near and fails with its time otherwise.
+            if limited && attempts < 2 {
+                continue;
+            }
+
+            if limited {
+                let until = limit.map(RateLimit::until).unwrap_or_default();
+
+                return Err(CommandError::RateLimited { until }.into());
+            }
+
+            break (status, headers, body);
+        };
+
+        let body = match (status, cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => {
+                debug!("{route} is unchanged");
+
+                // Written again so `cache prune` sees it was used.
+                if let Err(e) = self.store(route, &cached) {
+                    warn!("Unable to cache the response for {route}: {e}");
+                }
+
+                cached.body
+            }
+            (status, _) if status.is_success() => {
+                if let Some(etag) = headers.get(ETAG).and_then(|etag| etag.to_str().ok()) {
+                    let response = CachedResponse { etag: etag.to_string(), body };
+
+                    if let Err(e) = self.store(route, &response) {
+                        warn!("Unable to cache the response for {route}: {e}");
+                    }
+
+                    response.body
+                } else {
+                    body
+                }
+            }
+            (status, _) => {
+                #[derive(Deserialize)]
+                struct ErrorMessage {
+                    message: String,
+                }
+
+                let message = serde_json::from_str::<ErrorMessage>(&body).map_or(body, |error| error.message);
+
+                return Err(anyhow!("GitHub API returned {status} for {route}: {message}"));
+            }
+        };
+
+        serde_json::from_str(&body).context(format!("Unable to parse the response for {route}"))
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse {
+    data: Option<HashMap<String, Option<GraphQlRepository>>>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRepository {
+    latest_release: Option<GraphQlRelease>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRelease {
+    tag_name: String,
+    is_prerelease: bool,
+    is_draft: bool,
+    published_at: Option<String>,
+    url: String,
+    release_assets: GraphQlAssets,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlAssets {
+    total_count: usize,
+    nodes: Vec<GraphQlAsset>,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlAsset {
+    name: String,
+    download_url: Url,
+    size: u64,
+}
+
+impl From<GraphQlRelease> for Release {
+    fn from(release: GraphQlRelease) -> Self {
+        Self {
+            tag_name: release.tag_name,
+            prerelease: release.is_prerelease,
+            draft: release.is_draft,
+            published_at: release.published_at,
+            html_url: Some(release.url),
+            assets: release
+                .release_assets
+                .nodes
+                .into_iter()
+                .map(|asset| Asset {
+                    name: asset.name,
+                    browser_download_url: asset.download_url,
+                    size: asset.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<repos::Release> for Release {
+    fn from(release: repos::Release) -> Self {
+        Self {
+            tag_name: release.tag_name,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            published_at: release.published_at.map(|date| date.to_rfc3339()),
+            html_url: Some(release.html_url.to_string()),
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| Asset {
+                    name: asset.name,
+                    browser_download_url: asset.browser_download_url,
+                    size: u64::try_from(asset.size).unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ReleaseSource for GitHub {
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>> {
+        let releases: Vec<repos::Release> = self.get(&format!("/repos/{owner}/{repo}/releases?per_page=100")).await?;
+
+        Ok(releases.into_iter().map(Release::from).collect())
+    }
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release> {
+        let release: repos::Release = self.get(&format!("/repos/{owner}/{repo}/releases/latest")).await?;
+
+        Ok(release.into())
+    }
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release> {
+        let release: repos::Release = self.get(&format!("/repos/{owner}/{repo}/releases/tags/{}", super::encode_segment(tag))).await?;
+
+        Ok(release.into())
+    }
+}
@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Client, Url,
+};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{auth, config::Host, download};
+
+use super::{encode_segment, file_name, http_client, Asset, Release, ReleaseSource, SourceKind};
+
+pub struct GitLab {
+    client: Client,
+    api_url: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    released_at: Option<String>,
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(rename = "_links")]
+    links: Option<GitLabReleaseLinks>,
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize)]
+struct GitLabReleaseLinks {
+    #[serde(rename = "self")]
+    html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabAssetLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssetLink {
+    name: String,
+    url: Url,
+    direct_asset_url: Option<Url>,
+}
+
+impl From<GitLabRelease> for Release {
+    fn from(release: GitLabRelease) -> Self {
+        Self {
+            // GitLab has no pre-release flag, so fall back to the SemVer pre-release component.
+            prerelease: semver::Version::parse(release.tag_name.trim_start_matches('v')).is_ok_and(|v| !v.pre.is_empty()),
+            tag_name: release.tag_name,
+            draft: release.upcoming_release,
+            published_at: release.released_at,
+            html_url: release.links.and_then(|links| links.html_url),
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| {
+                    let url = link.direct_asset_url.unwrap_or(link.url);
+
+                    Asset {
+                        name: if link.name.is_empty() { file_name(&url) } else { link.name },
+                        browser_download_url: url,
+                        size: 0,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl GitLab {
+    pub fn new(host: &'_ str, settings: Host) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+
+        if let Some(token) = settings.token.or_else(|| auth::token(host, auth::variables(SourceKind::GitLab, host))) {
+            headers.insert("PRIVATE-TOKEN", HeaderValue::from_str(&token).context(format!("Invalid token for {host}"))?);
+
+            // Assets of private projects are downloaded from the same host, which also takes the token as a bearer token.
+            download::authorize(
+                host,
+                HeaderValue::from_str(&format!("Bearer {token}")).context(format!("Invalid token for {host}"))?,
+            );
+        }
+
+        Ok(Self {
+            client: http_client(headers)?,
+            api_url: settings.api_url.unwrap_or_else(|| format!("https://{host}/api/v4")),
+        })
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, owner: &'_ str, repo: &'_ str, path: &'_ str) -> Result<T> {
+        let url = format!("{}/projects/{}%2F{}/{path}", self.api_url, owner.replace('/', "%2F"), repo);
+
+        debug!("GitLab GET {url}");
+
+        Ok(self.client.get(&url).send().await?.error_for_status()?.json().await?)
+    }
+}
+
+impl ReleaseSource for GitLab {
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>> {
+        let releases: Vec<GitLabRelease> = self.get(owner, repo, "releases?per_page=100").await?;
+
+        Ok(releases.into_iter().map(Release::from).collect())
+    }
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release> {
+        let release: GitLabRelease = self.get(owner, repo, "releases/permalink/latest").await?;
+
+        Ok(release.into())
+    }
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release> {
+        let release: GitLabRelease = self.get(owner, repo, &format!("releases/{}", encode_segment(tag))).await?;
+
+        Ok(release.into())
+    }
+}
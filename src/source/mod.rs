@@ -0,0 +1,177 @@
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+pub mod template;
+
+use std::fmt::Display;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{Config, Package},
+    errors::CommandError,
+    system::System,
+};
+
+use self::{gitea::Gitea, github::GitHub, gitlab::GitLab, template::Template};
+
+/// A release, normalized across all of the supported backends.
+#[derive(Debug, Clone, Default)]
+pub struct Release {
+    pub tag_name: String,
+    pub prerelease: bool,
+    pub draft: bool,
+    pub published_at: Option<String>,
+    pub html_url: Option<String>,
+    pub assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: Url,
+    pub size: u64,
+}
+
+/// Where the releases for a package are published.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lower")]
+pub enum SourceKind {
+    #[default]
+    GitHub,
+    GitLab,
+    Gitea,
+    Template,
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            SourceKind::GitHub => write!(f, "GitHub"),
+            SourceKind::GitLab => write!(f, "GitLab"),
+            SourceKind::Gitea => write!(f, "Gitea"),
+            SourceKind::Template => write!(f, "URL template"),
+        }
+    }
+}
+
+impl SourceKind {
+    pub fn default_host(self) -> Option<&'static str> {
+        match self {
+            Self::GitHub => Some("github.com"),
+            Self::GitLab => Some("gitlab.com"),
+            Self::Gitea | Self::Template => None,
+        }
+    }
+
+    /// Guess the kind of forge from a host name.
+    pub fn detect(host: &'_ str) -> Option<Self> {
+        let host = host.to_lowercase();
+
+        if host == "github.com" {
+            Some(Self::GitHub)
+        } else if host.contains("gitlab") {
+            Some(Self::GitLab)
+        } else if host == "codeberg.org" || host.contains("gitea") || host.contains("forgejo") {
+            Some(Self::Gitea)
+        } else {
+            None
+        }
+    }
+}
+
+pub trait ReleaseSource {
+    /// All releases for the repository, newest first.
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>>;
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release>;
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release>;
+}
+
+pub enum Source {
+    GitHub(GitHub),
+    GitLab(GitLab),
+    Gitea(Gitea),
+    Template(Template),
+}
+
+impl Source {
+    pub fn for_package(config: &'_ Config, package: &'_ Package, system: &'_ System) -> Result<Self> {
+        let kind = config.source_kind(package);
+        let host = package.host.as_deref().or_else(|| kind.default_host());
+        let settings = host.and_then(|host| config.hosts.get(host)).cloned().unwrap_or_default();
+
+        let missing_host = || CommandError::MissingHost {
+            name: package.name.clone(),
+            source_kind: kind,
+        };
+
+        Ok(match kind {
+            SourceKind::GitHub => Self::GitHub(GitHub::new(host.ok_or_else(missing_host)?, settings)?),
+            SourceKind::GitLab => Self::GitLab(GitLab::new(host.ok_or_else(missing_host)?, settings)?),
+            SourceKind::Gitea => Self::Gitea(Gitea::new(host.ok_or_else(missing_host)?, settings)?),
+            SourceKind::Template => Self::Template(Template::new(package, &system.for_package(package))?),
+        })
+    }
+}
+
+impl ReleaseSource for Source {
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>> {
+        match self {
+            Self::GitHub(s) => s.releases(owner, repo).await,
+            Self::GitLab(s) => s.releases(owner, repo).await,
+            Self::Gitea(s) => s.releases(owner, repo).await,
+            Self::Template(s) => s.releases(owner, repo).await,
+        }
+    }
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release> {
+        match self {
+            Self::GitHub(s) => s.latest_release(owner, repo).await,
+            Self::GitLab(s) => s.latest_release(owner, repo).await,
+            Self::Gitea(s) => s.latest_release(owner, repo).await,
+            Self::Template(s) => s.latest_release(owner, repo).await,
+        }
+    }
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release> {
+        match self {
+            Self::GitHub(s) => s.release_by_tag(owner, repo, tag).await,
+            Self::GitLab(s) => s.release_by_tag(owner, repo, tag).await,
+            Self::Gitea(s) => s.release_by_tag(owner, repo, tag).await,
+            Self::Template(s) => s.release_by_tag(owner, repo, tag).await,
+        }
+    }
+}
+
+/// HTTP client shared by the non-GitHub backends.
+pub fn http_client(headers: reqwest::header::HeaderMap) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")))
+        .default_headers(headers)
+        .build()
+        .context("Unable to build HTTP client")
+}
+
+/// Percent-encode `segment` for a URL path, e.g. tags such as `release/1.0` or `1.0+build`.
+pub fn encode_segment(segment: &'_ str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => char::from(byte).to_string(),
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+pub fn file_name(url: &'_ Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .unwrap_or_default()
+        .to_string()
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use reqwest::{StatusCode, Url};
+use strfmt::strfmt;
+use tracing::debug;
+
+use crate::{config::Package, download, errors::CommandError, system::System};
+
+use super::{file_name, Asset, Release, ReleaseSource};
+
+/// Releases published at a predictable URL rather than through a forge API.
+///
+/// The URL template may reference `{owner}`, `{repo}`, `{version}`, `{os}` and `{arch}`.
+/// Without a `latest_url` (which must return the newest version as plain text) a version has to be given explicitly.
+pub struct Template {
+    name: String,
+    url: String,
+    latest_url: Option<String>,
+    os: String,
+    arch: String,
+}
+
+impl Template {
+    pub fn new(package: &'_ Package, system: &'_ System) -> Result<Self> {
+        let Some(url_template) = package.url_template.clone() else {
+            return Err(CommandError::MissingUrlTemplate { name: package.name.clone() }.into());
+        };
+
+        Ok(Self {
+            name: package.name.clone(),
+            url: url_template,
+            latest_url: package.latest_url.clone(),
+            os: system.os.to_string().to_lowercase(),
+            arch: system.architecture.to_string().to_lowercase(),
+        })
+    }
+}
+
+impl ReleaseSource for Template {
+    async fn releases(&self, owner: &'_ str, repo: &'_ str) -> Result<Vec<Release>> {
+        // Only a missing release means there are none, failing to reach the host or being refused isn't.
+        match self.latest_release(owner, repo).await {
+            Ok(release) => Ok(vec![release]),
+            Err(e) if matches!(e.downcast_ref::<CommandError>(), Some(CommandError::ReleaseNotFound(_))) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn latest_release(&self, owner: &'_ str, repo: &'_ str) -> Result<Release> {
+        let Some(latest_url) = &self.latest_url else {
+            return Err(CommandError::ReleaseNotFound(self.name.clone()).into());
+        };
+
+        let url = Url::parse(latest_url).map_err(|_| CommandError::InvalidUrl(latest_url.clone()))?;
+
+        let version = match download::text(&url).await {
+            Ok(version) => version,
+            Err(e) if e.downcast_ref::<reqwest::Error>().and_then(reqwest::Error::status) == Some(StatusCode::NOT_FOUND) => {
+                return Err(CommandError::ReleaseNotFound(self.name.clone()).into());
+            }
+            Err(e) => return Err(e.context(format!("Unable to get the latest version of {}", self.name))),
+        };
+
+        self.release_by_tag(owner, repo, version.trim()).await
+    }
+
+    async fn release_by_tag(&self, owner: &'_ str, repo: &'_ str, tag: &'_ str) -> Result<Release> {
+        let vars = HashMap::from([
+            ("owner".to_string(), owner.to_string()),
+            ("repo".to_string(), repo.to_string()),
+            ("version".to_string(), tag.trim_start_matches('v').to_string()),
+            ("os".to_string(), self.os.clone()),
+            ("arch".to_string(), self.arch.clone()),
+        ]);
+
+        let url = strfmt(&self.url, &vars).context(format!("Invalid URL template: {}", self.url))?;
+        let url = Url::parse(&url).map_err(|_| CommandError::InvalidUrl(url.clone()))?;
+
+        debug!("Resolved URL template to {url}");
+
+        Ok(Release {
+            tag_name: tag.to_string(),
+            assets: vec![Asset {
+                name: file_name(&url),
+                browser_download_url: url,
+                size: 0,
+            }],
+            ..Default::default()
+        })
+    }
+}
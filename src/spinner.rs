@@ -1,17 +1,57 @@
+use std::sync::OnceLock;
 use std::time::Duration;
 
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::output;
+
+static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
+
+fn style() -> ProgressStyle {
+    ProgressStyle::with_template("{msg} {spinner:.cyan.bold}")
+        .expect("Failed to set progress style.")
+        .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ ")
+}
+
+/// Every spinner and progress bar is drawn through this, so concurrent ones don't overwrite each other.
+///
+/// Nothing is drawn with structured output.
+fn progress() -> &'static MultiProgress {
+    PROGRESS.get_or_init(|| {
+        if output::is_structured() {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        }
+    })
+}
 
 pub fn spinner() -> ProgressBar {
-    let pb = ProgressBar::new_spinner();
+    let pb = progress().add(ProgressBar::new_spinner());
+
+    pb.set_style(style());
 
     pb.enable_steady_tick(Duration::from_millis(50));
 
+    pb
+}
+
+/// Hide every spinner and progress bar while `f` runs, e.g. while prompting.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    progress().suspend(f)
+}
+
+/// Byte progress of downloading `name`, with throughput and ETA once the length is known.
+pub fn download_bar(name: &'_ str) -> ProgressBar {
+    let pb = progress().add(ProgressBar::no_length());
+
     pb.set_style(
-        ProgressStyle::with_template("{msg} {spinner:.cyan.bold}")
+        ProgressStyle::with_template("  {msg} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {bytes_per_sec} ETA {eta}")
             .expect("Failed to set progress style.")
-            .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ "),
+            .progress_chars("━╸ "),
     );
 
+    pb.set_message(name.to_string());
+
     pb
 }
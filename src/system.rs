@@ -1,13 +1,21 @@
 use std::env::consts::{ARCH, OS};
 use std::fmt::Display;
+use std::fs;
+use std::str::FromStr;
 
+use clap::{Args, ValueEnum};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
+use crate::config::Package;
+
 #[derive(Debug, Clone)]
 pub struct System {
     pub architecture: PlatformArchitecture,
     pub os: OperatingSystem,
+    /// Preferred C library when a release ships Linux assets for both.
+    pub libc: Libc,
 }
 
 impl Default for System {
@@ -16,18 +24,91 @@ impl Default for System {
             architecture: match ARCH {
                 "x86_64" => PlatformArchitecture::Amd64,
                 "aarch64" => PlatformArchitecture::Arm64,
-                _ => panic!("Running on a unknown system architecture!"),
+                "arm" if cfg!(target_feature = "v7") => PlatformArchitecture::Armv7,
+                "arm" => PlatformArchitecture::Armv6,
+                "x86" => PlatformArchitecture::I686,
+                "riscv64" => PlatformArchitecture::Riscv64,
+                "powerpc64" if cfg!(target_endian = "little") => PlatformArchitecture::Ppc64le,
+                "s390x" => PlatformArchitecture::S390x,
+                other => PlatformArchitecture::Unknown(other.to_string()),
             },
             os: match OS {
                 "linux" => OperatingSystem::Linux,
                 "macos" => OperatingSystem::Darwin,
-                _ => panic!("Running on a unknown OS!"),
+                "freebsd" => OperatingSystem::FreeBsd,
+                "netbsd" => OperatingSystem::NetBsd,
+                "openbsd" => OperatingSystem::OpenBsd,
+                other => OperatingSystem::Unknown(other.to_string()),
             },
+            libc: Libc::detect(),
+        }
+    }
+}
+
+/// Match any of `alternatives` as a whole word, ignoring case.
+///
+/// Only letters and digits count as part of a word, `\b` would also treat `_` as one and miss `x86_64` in
+/// `tool_linux_x86_64.tar.gz`.
+fn word_regex(alternatives: &'_ str) -> Regex {
+    Regex::new(&format!(r"(?i)(?:^|[^a-z0-9])(?:{alternatives})(?:[^a-z0-9]|$)")).expect("Unable to create platform regex")
+}
+
+/// Platform to install for, instead of the one released is running on.
+#[derive(Debug, Clone, Default, Args)]
+pub struct Target {
+    /// Operating system to install binaries for, e.g. `linux` or `macos`.
+    #[arg(long, value_name = "OS")]
+    pub os: Option<OperatingSystem>,
+    /// Architecture to install binaries for, e.g. `arm64` or `x86_64`.
+    #[arg(long, value_name = "ARCH")]
+    pub arch: Option<PlatformArchitecture>,
+    /// C library to prefer for Linux binaries.
+    #[arg(long, value_enum)]
+    pub libc: Option<Libc>,
+}
+
+impl Target {
+    /// Override the platform `package` is installed for.
+    pub fn apply(&self, package: Package) -> Package {
+        Package {
+            os: self.os.clone().or(package.os),
+            arch: self.arch.clone().or(package.arch),
+            libc: self.libc.or(package.libc),
+            ..package
         }
     }
 }
 
 impl System {
+    /// The platform `package` is installed for, when it was added for a different one.
+    pub fn for_package(&self, package: &'_ Package) -> Self {
+        Self {
+            os: package.os.clone().unwrap_or_else(|| self.os.clone()),
+            architecture: package.arch.clone().unwrap_or_else(|| self.architecture.clone()),
+            libc: package.libc.unwrap_or(self.libc),
+        }
+    }
+
+    /// Name of the platform, e.g. `linux-x86_64-gnu` or `macos-arm64`, which assets are locked for.
+    pub fn target(&self) -> String {
+        let target = format!("{}-{}", self.os, self.architecture).to_lowercase();
+
+        // The C library only decides between assets on Linux.
+        if self.os == OperatingSystem::Linux {
+            format!("{target}-{}", self.libc)
+        } else {
+            target
+        }
+    }
+
+    /// Use the configured C library preference, if there is one.
+    pub fn with_libc(self, libc: Option<Libc>) -> Self {
+        Self {
+            libc: libc.unwrap_or(self.libc),
+            ..self
+        }
+    }
+
     #[allow(dead_code)]
     pub fn is_match(&self, s: &'_ str) -> bool {
         self.is_os_match(s) && (self.is_arch_match(s) || Self::is_universal_match(s))
@@ -46,9 +127,18 @@ impl System {
 
         debug!("Architecture Regex[{}], trying to match {}", arch_regex.to_string(), s);
 
+        // `x86` is also how `x86_64` starts, which can't be ruled out without a look-ahead.
+        if self.architecture == PlatformArchitecture::I686 && PlatformArchitecture::Amd64.get_match_regex().is_match(s) {
+            return false;
+        }
+
         arch_regex.is_match(s)
     }
 
+    pub fn is_libc_match(&self, s: &'_ str) -> bool {
+        self.libc.get_match_regex().is_match(s)
+    }
+
     #[allow(dead_code)]
     pub fn is_universal_match(s: &'_ str) -> bool {
         debug!("macOS Universal trying to match: {}", s);
@@ -58,50 +148,301 @@ impl System {
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum OperatingSystem {
     Linux,
     Darwin,
+    FreeBsd,
+    NetBsd,
+    OpenBsd,
+    /// Anything else, matched by the name Rust uses for it.
+    Unknown(String),
 }
 
 impl Display for OperatingSystem {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
+        match self {
             OperatingSystem::Linux => write!(f, "Linux"),
             OperatingSystem::Darwin => write!(f, "macOS"),
+            OperatingSystem::FreeBsd => write!(f, "FreeBSD"),
+            OperatingSystem::NetBsd => write!(f, "NetBSD"),
+            OperatingSystem::OpenBsd => write!(f, "OpenBSD"),
+            OperatingSystem::Unknown(os) => write!(f, "{os}"),
         }
     }
 }
 
 impl OperatingSystem {
-    pub fn get_match_regex(&self) -> Regex {
+    const KNOWN: [Self; 5] = [Self::Linux, Self::Darwin, Self::FreeBsd, Self::NetBsd, Self::OpenBsd];
+
+    /// Alternative spellings used in asset names, to be inserted into a larger pattern.
+    pub fn pattern(&self) -> String {
         match self {
-            Self::Linux => Regex::new(r"(linux|unknown-linux-gnu)").expect("Unable to create regex for Linux"),
-            Self::Darwin => Regex::new(r"(mac|macos|darwin)").expect("Unable to create regex for macOS"),
+            Self::Linux => "(linux|linux32|linux64)".to_string(),
+            Self::Darwin => "(darwin|apple-darwin|macos|macosx|mac|osx)".to_string(),
+            Self::FreeBsd => "(freebsd)".to_string(),
+            Self::NetBsd => "(netbsd)".to_string(),
+            Self::OpenBsd => "(openbsd)".to_string(),
+            Self::Unknown(os) => format!("({})", regex::escape(os)),
         }
     }
+
+    pub fn get_match_regex(&self) -> Regex {
+        word_regex(&self.pattern())
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum PlatformArchitecture {
     Amd64,
     Arm64,
+    Armv7,
+    Armv6,
+    I686,
+    Riscv64,
+    Ppc64le,
+    S390x,
+    /// Anything else, matched by the name Rust uses for it.
+    Unknown(String),
 }
 
 impl Display for PlatformArchitecture {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
+        match self {
             PlatformArchitecture::Amd64 => write!(f, "x86_64"),
             PlatformArchitecture::Arm64 => write!(f, "arm64"),
+            PlatformArchitecture::Armv7 => write!(f, "armv7"),
+            PlatformArchitecture::Armv6 => write!(f, "armv6"),
+            PlatformArchitecture::I686 => write!(f, "i686"),
+            PlatformArchitecture::Riscv64 => write!(f, "riscv64"),
+            PlatformArchitecture::Ppc64le => write!(f, "ppc64le"),
+            PlatformArchitecture::S390x => write!(f, "s390x"),
+            PlatformArchitecture::Unknown(arch) => write!(f, "{arch}"),
         }
     }
 }
 
 impl PlatformArchitecture {
+    // ARMv6 goes after ARMv7, so a bare `arm` parses as the latter.
+    const KNOWN: [Self; 8] = [
+        Self::Amd64,
+        Self::Arm64,
+        Self::Armv7,
+        Self::Armv6,
+        Self::I686,
+        Self::Riscv64,
+        Self::Ppc64le,
+        Self::S390x,
+    ];
+
+    /// Alternative spellings used in asset names, to be inserted into a larger pattern.
+    ///
+    /// A bare `arm` usually means ARMv6, which ARMv7 can run too.
+    pub fn pattern(&self) -> String {
+        match self {
+            Self::Amd64 => "(x86_64|x86-64|amd64|x64)".to_string(),
+            Self::Arm64 => "(aarch64|arm64|arm64e|armv8|armv8-a)".to_string(),
+            Self::Armv7 => "(armv7|armv7l|armv7a|armv7hf|arm7|armhf|arm)".to_string(),
+            Self::Armv6 => "(armv6|armv6l|armv6hf|arm6|armel|arm)".to_string(),
+            Self::I686 => "(i686|i586|i486|i386|386|x86|x86-32|ia32)".to_string(),
+            Self::Riscv64 => "(riscv64|riscv64gc)".to_string(),
+            Self::Ppc64le => "(ppc64le|powerpc64le)".to_string(),
+            Self::S390x => "(s390x)".to_string(),
+            Self::Unknown(arch) => format!("({})", regex::escape(arch)),
+        }
+    }
+
     pub fn get_match_regex(&self) -> Regex {
+        word_regex(&self.pattern())
+    }
+}
+
+/// Parse any of the spellings matched in asset names, e.g. `macos`, `aarch64` or `x64`.
+impl OperatingSystem {
+    /// One of the `KNOWN` values, from any of its spellings.
+    fn parse_known(s: &'_ str) -> Option<Self> {
+        let whole = |os: &Self| Regex::new(&format!("(?i)^{}$", os.pattern())).is_ok_and(|regex| regex.is_match(s));
+
+        Self::KNOWN.into_iter().find(whole)
+    }
+}
+
+/// Parses command line values, rejecting spellings which aren't known.
+impl FromStr for OperatingSystem {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_known(s).ok_or_else(|| {
+            let known: Vec<String> = Self::KNOWN.into_iter().map(String::from).collect();
+
+            format!("unknown operating system '{s}', expected one of: {}", known.join(", "))
+        })
+    }
+}
+
+/// Deserializes existing config, keeping values which aren't known.
+impl From<String> for OperatingSystem {
+    fn from(s: String) -> Self {
+        Self::parse_known(&s).unwrap_or(Self::Unknown(s))
+    }
+}
+
+impl From<OperatingSystem> for String {
+    fn from(os: OperatingSystem) -> Self {
+        os.to_string().to_lowercase()
+    }
+}
+
+impl PlatformArchitecture {
+    /// One of the `KNOWN` values, from any of its spellings.
+    fn parse_known(s: &'_ str) -> Option<Self> {
+        let whole = |arch: &Self| Regex::new(&format!("(?i)^{}$", arch.pattern())).is_ok_and(|regex| regex.is_match(s));
+
+        Self::KNOWN.into_iter().find(whole)
+    }
+}
+
+/// Parses command line values, rejecting spellings which aren't known.
+impl FromStr for PlatformArchitecture {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_known(s).ok_or_else(|| {
+            let known: Vec<String> = Self::KNOWN.into_iter().map(String::from).collect();
+
+            format!("unknown architecture '{s}', expected one of: {}", known.join(", "))
+        })
+    }
+}
+
+/// Deserializes existing config, keeping values which aren't known.
+impl From<String> for PlatformArchitecture {
+    fn from(s: String) -> Self {
+        Self::parse_known(&s).unwrap_or(Self::Unknown(s))
+    }
+}
+
+impl From<PlatformArchitecture> for String {
+    fn from(arch: PlatformArchitecture) -> Self {
+        arch.to_string()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lower")]
+pub enum Libc {
+    #[default]
+    Gnu,
+    Musl,
+}
+
+impl Display for Libc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Libc::Gnu => write!(f, "gnu"),
+            Libc::Musl => write!(f, "musl"),
+        }
+    }
+}
+
+impl Libc {
+    /// Musl based distributions, such as Alpine, ship the dynamic loader as `/lib/ld-musl-<arch>.so.1`.
+    pub fn detect() -> Self {
+        let is_musl = OS == "linux"
+            && fs::read_dir("/lib").is_ok_and(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .any(|entry| entry.file_name().to_string_lossy().starts_with("ld-musl-"))
+            });
+
+        if is_musl {
+            debug!("Found the musl dynamic loader, preferring musl assets");
+
+            Libc::Musl
+        } else {
+            Libc::Gnu
+        }
+    }
+
+    /// The other C library, for assets built against it.
+    pub fn other(self) -> Self {
+        match self {
+            Libc::Gnu => Libc::Musl,
+            Libc::Musl => Libc::Gnu,
+        }
+    }
+
+    pub fn get_match_regex(self) -> Regex {
         match self {
-            Self::Amd64 => Regex::new(r"(amd64|x86_64)").expect("Unable to create regex for amd64"),
-            Self::Arm64 => Regex::new(r"(arm64|aarch64)").expect("Unable to create regex for arm64"),
+            Libc::Gnu => word_regex("gnu|gnueabi|gnueabihf|glibc"),
+            Libc::Musl => word_regex("musl|musleabi|musleabihf|alpine"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linux(architecture: PlatformArchitecture, libc: Libc) -> System {
+        System {
+            architecture,
+            os: OperatingSystem::Linux,
+            libc,
+        }
+    }
+
+    #[test]
+    fn word_regex_treats_underscores_as_separators() {
+        let regex = word_regex(&PlatformArchitecture::Amd64.pattern());
+
+        assert!(regex.is_match("tool_linux_x86_64.tar.gz"));
+        assert!(regex.is_match("tool-X86_64-linux.tar.gz"));
+        assert!(!regex.is_match("tool-linux-x86_64v2.tar.gz"));
+    }
+
+    #[test]
+    fn x86_is_not_x86_64() {
+        let i686 = linux(PlatformArchitecture::I686, Libc::Gnu);
+        let amd64 = linux(PlatformArchitecture::Amd64, Libc::Gnu);
+
+        assert!(i686.is_arch_match("tool-linux-x86.tar.gz"));
+        assert!(i686.is_arch_match("tool_linux_386.tar.gz"));
+        assert!(!i686.is_arch_match("tool-linux-x86_64.tar.gz"));
+        assert!(!i686.is_arch_match("tool-linux-x86-64.tar.gz"));
+
+        assert!(amd64.is_arch_match("tool-linux-x86_64.tar.gz"));
+        assert!(amd64.is_arch_match("tool-linux-amd64.tar.gz"));
+        assert!(!amd64.is_arch_match("tool-linux-x86.tar.gz"));
+    }
+
+    #[test]
+    fn arm_is_not_arm64() {
+        let arm32 = linux(PlatformArchitecture::Armv7, Libc::Gnu);
+        let aarch64 = linux(PlatformArchitecture::Arm64, Libc::Gnu);
+
+        assert!(arm32.is_arch_match("tool-linux-arm.tar.gz"));
+        assert!(arm32.is_arch_match("tool-linux-armv7.tar.gz"));
+        assert!(!arm32.is_arch_match("tool-linux-arm64.tar.gz"));
+
+        assert!(aarch64.is_arch_match("tool-linux-arm64.tar.gz"));
+        assert!(aarch64.is_arch_match("tool-aarch64-unknown-linux-gnu.tar.gz"));
+        assert!(!aarch64.is_arch_match("tool-linux-arm.tar.gz"));
+    }
+
+    #[test]
+    fn musl_is_not_gnu() {
+        let gnu = linux(PlatformArchitecture::Amd64, Libc::Gnu);
+        let musl = linux(PlatformArchitecture::Amd64, Libc::Musl);
+
+        assert!(gnu.is_libc_match("tool-x86_64-unknown-linux-gnu.tar.gz"));
+        assert!(!gnu.is_libc_match("tool-x86_64-unknown-linux-musl.tar.gz"));
+
+        assert!(musl.is_libc_match("tool-x86_64-unknown-linux-musl.tar.gz"));
+        assert!(musl.is_libc_match("tool-armv7-unknown-linux-musleabihf.tar.gz"));
+        assert!(!musl.is_libc_match("tool-armv7-unknown-linux-gnueabihf.tar.gz"));
+    }
+}
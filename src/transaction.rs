@@ -0,0 +1,188 @@
+use std::fs::{self, File, Permissions};
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use tracing::{debug, error, warn};
+
+// Set by Ctrl-C while files are being replaced, so the transactions roll back before exiting.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// How many transactions are in progress, guarding the decision to exit.
+static ACTIVE: Mutex<usize> = Mutex::new(0);
+
+// The conventional exit status after SIGINT.
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// Handle Ctrl-C: exit straight away, unless installed files are being replaced.
+///
+/// Then each transaction fails at its next file and rolls back, and the last one to finish exits.
+pub fn interrupt() {
+    let active = ACTIVE.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    INTERRUPTED.store(true, Ordering::SeqCst);
+
+    if *active == 0 {
+        std::process::exit(INTERRUPTED_STATUS);
+    }
+
+    warn!("Interrupted, rolling back the files being installed ...");
+}
+
+fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Atomically replaces installed files, keeping the previous versions until `commit` is called.
+///
+/// Dropping an uncommitted transaction restores every file it replaced.
+#[derive(Debug)]
+pub struct Transaction {
+    replaced: Vec<Replaced>,
+    committed: bool,
+}
+
+#[derive(Debug)]
+struct Replaced {
+    destination: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+fn sibling(destination: &'_ Path, suffix: &'_ str) -> PathBuf {
+    let file_name = destination.file_name().unwrap_or_default().to_string_lossy();
+
+    destination.with_file_name(format!(".{file_name}.{}.released-{suffix}", std::process::id()))
+}
+
+fn stage(source: &'_ Path, temp: &'_ Path, mode: u32) -> io::Result<()> {
+    let mut reader = File::open(source)?;
+    let mut writer = File::create(temp)?;
+
+    io::copy(&mut reader, &mut writer)?;
+    writer.set_permissions(Permissions::from_mode(mode))?;
+    writer.sync_all()
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        *ACTIVE.lock().unwrap_or_else(std::sync::PoisonError::into_inner) += 1;
+
+        Self {
+            replaced: Vec::new(),
+            committed: false,
+        }
+    }
+}
+
+impl Transaction {
+    /// Copy `source` over `destination` with `mode` permissions.
+    ///
+    /// The file is written to a temporary sibling, synced and then renamed into place, so `destination` is never partially written.
+    pub fn install(&mut self, source: &'_ Path, destination: &'_ Path, mode: u32) -> Result<()> {
+        self.check_interrupted()?;
+
+        let temp = sibling(destination, "tmp");
+
+        debug!("Staging {source:?} at {temp:?}");
+
+        if let Err(e) = stage(source, &temp, mode) {
+            let _ = fs::remove_file(&temp);
+
+            return Err(e).context(format!("Unable to copy {source:?} to {temp:?}"));
+        }
+
+        // Keep the previous file around via a hard link, so `destination` always exists while it is swapped.
+        let backup = if destination.exists() {
+            let backup = sibling(destination, "bak");
+
+            let _ = fs::remove_file(&backup);
+
+            fs::hard_link(destination, &backup)
+                .or_else(|_| fs::copy(destination, &backup).map(|_| ()))
+                .context(format!("Unable to back up {destination:?}"))?;
+
+            Some(backup)
+        } else {
+            None
+        };
+
+        if let Err(e) = fs::rename(&temp, destination) {
+            let _ = fs::remove_file(&temp);
+
+            if let Some(backup) = backup {
+                let _ = fs::remove_file(backup);
+            }
+
+            return Err(e).context(format!("Unable to move {temp:?} to {destination:?}"));
+        }
+
+        if let Some(parent) = destination.parent() {
+            let _ = File::open(parent).and_then(|dir| dir.sync_all());
+        }
+
+        self.replaced.push(Replaced {
+            destination: destination.to_path_buf(),
+            backup,
+        });
+
+        Ok(())
+    }
+
+    /// Fail if Ctrl-C was pressed, so the transaction is dropped and rolls back before anything else is saved.
+    #[allow(clippy::unused_self)]
+    pub fn check_interrupted(&self) -> Result<()> {
+        if is_interrupted() {
+            bail!("Interrupted");
+        }
+
+        Ok(())
+    }
+
+    /// Keep the new files and discard the backups of the previous ones.
+    ///
+    /// An interrupted process exits once the transaction is dropped, so keep it until the cleanup is done.
+    pub fn commit(&mut self) {
+        for replaced in &self.replaced {
+            if let Some(backup) = &replaced.backup {
+                let _ = fs::remove_file(backup);
+            }
+        }
+
+        self.committed = true;
+    }
+
+    fn rollback(&mut self) {
+        for replaced in self.replaced.drain(..).rev() {
+            debug!("Rolling back {:?}", replaced.destination);
+
+            let restored = match &replaced.backup {
+                Some(backup) => fs::rename(backup, &replaced.destination),
+                None => fs::remove_file(&replaced.destination),
+            };
+
+            if let Err(e) = restored {
+                error!("Unable to restore {:?}: {e}", replaced.destination);
+            }
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.rollback();
+        }
+
+        let mut active = ACTIVE.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        *active -= 1;
+
+        // Everything interrupted has been rolled back or committed, finish what Ctrl-C started.
+        if *active == 0 && is_interrupted() {
+            std::process::exit(INTERRUPTED_STATUS);
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+use minisign_verify::{PublicKey, Signature as MinisignSignature};
+use tracing::debug;
+
+use crate::{config::Signature, source::Asset};
+
+// Aggregate checksum files covering every asset in a release.
+const CHECKSUM_FILES: [&str; 5] = ["checksums.txt", "sha256sums", "sha256sums.txt", "checksums.sha256", "sha256.txt"];
+
+/// Find the checksum asset published alongside `asset`.
+///
+/// Per-asset files (`foo.tar.gz.sha256`) are preferred over aggregate files such as `checksums.txt` or `SHA256SUMS`.
+pub fn checksum_asset<'a>(assets: &'a [Asset], asset: &'_ Asset) -> Option<&'a Asset> {
+    let per_asset = [format!("{}.sha256", asset.name), format!("{}.sha256sum", asset.name)];
+
+    assets.iter().find(|a| per_asset.contains(&a.name)).or_else(|| {
+        assets.iter().find(|a| {
+            let name = a.name.to_lowercase();
+
+            CHECKSUM_FILES
+                .iter()
+                .any(|suffix| name == *suffix || name.ends_with(&format!("_{suffix}")) || name.ends_with(&format!("-{suffix}")))
+        })
+    })
+}
+
+/// Extract the expected SHA-256 digest for `asset_name` from the contents of a checksum file.
+///
+/// Handles bare digests, GNU coreutils (`<digest>  [*]<file>`) and BSD (`SHA256 (<file>) = <digest>`) formats.
+pub fn expected_digest(contents: &'_ str, asset_name: &'_ str) -> Option<String> {
+    for line in contents.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("SHA256 (") {
+            if let Some((file, digest)) = rest.split_once(") = ") {
+                if file_matches(file, asset_name) && is_sha256(digest) {
+                    return Some(digest.to_lowercase());
+                }
+            }
+
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+
+        let Some(digest) = parts.next().filter(|d| is_sha256(d)) else {
+            continue;
+        };
+
+        match parts.next() {
+            None => return Some(digest.to_lowercase()),
+            Some(file) if file_matches(file, asset_name) => return Some(digest.to_lowercase()),
+            Some(_) => {}
+        }
+    }
+
+    debug!("No checksum entry found for {asset_name}");
+
+    None
+}
+
+fn file_matches(file: &'_ str, asset_name: &'_ str) -> bool {
+    file.trim_start_matches('*').rsplit('/').next() == Some(asset_name)
+}
+
+fn is_sha256(s: &'_ str) -> bool {
+    s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Find the signature asset matching `signature`'s policy that was published alongside `asset`.
+pub fn signature_asset<'a>(assets: &'a [Asset], asset: &'_ Asset, signature: &'_ Signature) -> Option<&'a Asset> {
+    let candidates = match signature {
+        Signature::Minisign { .. } => vec![format!("{}.minisig", asset.name)],
+        Signature::Cosign { .. } => vec![
+            format!("{}.bundle", asset.name),
+            format!("{}.sigstore.json", asset.name),
+            format!("{}.sigstore", asset.name),
+        ],
+    };
+
+    assets.iter().find(|a| candidates.contains(&a.name))
+}
+
+/// Verify `file` against the downloaded `signature_file` according to the package's policy.
+pub fn verify_signature(signature: &'_ Signature, file: &'_ Path, signature_file: &'_ Path) -> Result<()> {
+    match signature {
+        Signature::Minisign { public_key } => {
+            let public_key = PublicKey::from_base64(public_key).map_err(|e| anyhow!("Invalid minisign public key: {e}"))?;
+            let signature = MinisignSignature::from_file(signature_file).map_err(|e| anyhow!("Invalid minisign signature: {e}"))?;
+            let contents = fs::read(file).context(format!("Unable to read {}", file.display()))?;
+
+            public_key.verify(&contents, &signature, true).map_err(|e| anyhow!("{e}"))
+        }
+        Signature::Cosign { identity, issuer } => {
+            debug!("Running cosign verify-blob for {}", file.display());
+
+            let output = Command::new("cosign")
+                .arg("verify-blob")
+                .arg("--bundle")
+                .arg(signature_file)
+                .args(["--certificate-identity", identity.as_str(), "--certificate-oidc-issuer", issuer.as_str()])
+                .arg(file)
+                .output()
+                .context("Unable to run `cosign`, is it installed and in $PATH?")?;
+
+            if !output.status.success() {
+                bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::*;
+
+    const LINUX: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+    const DARWIN: &str = "60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752";
+
+    fn asset(name: &'_ str) -> Asset {
+        Asset {
+            name: name.to_string(),
+            browser_download_url: Url::parse(&format!("https://example.com/download/{name}")).unwrap(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn gnu_format() {
+        let contents = format!("{LINUX}  tool-linux.tar.gz\n{DARWIN} *tool-darwin.tar.gz\n");
+
+        assert_eq!(expected_digest(&contents, "tool-linux.tar.gz").as_deref(), Some(LINUX));
+        assert_eq!(expected_digest(&contents, "tool-darwin.tar.gz").as_deref(), Some(DARWIN));
+        assert_eq!(expected_digest(&contents, "tool-windows.zip"), None);
+    }
+
+    #[test]
+    fn gnu_format_with_directories() {
+        let contents = format!("{LINUX}  ./dist/tool-linux.tar.gz\n");
+
+        assert_eq!(expected_digest(&contents, "tool-linux.tar.gz").as_deref(), Some(LINUX));
+    }
+
+    #[test]
+    fn bsd_format() {
+        let contents = format!("SHA256 (tool-darwin.tar.gz) = {DARWIN}\nSHA256 (tool-linux.tar.gz) = {LINUX}\n");
+
+        assert_eq!(expected_digest(&contents, "tool-linux.tar.gz").as_deref(), Some(LINUX));
+        assert_eq!(expected_digest(&contents, "tool.tar.gz"), None);
+    }
+
+    #[test]
+    fn bare_digest() {
+        assert_eq!(expected_digest(&format!("{LINUX}\n"), "tool-linux.tar.gz").as_deref(), Some(LINUX));
+    }
+
+    #[test]
+    fn digests_are_lowercased() {
+        let contents = format!("{}  tool-linux.tar.gz", LINUX.to_uppercase());
+
+        assert_eq!(expected_digest(&contents, "tool-linux.tar.gz").as_deref(), Some(LINUX));
+    }
+
+    #[test]
+    fn ignores_other_digests() {
+        // SHA-1 and SHA-512 digests aren't mistaken for SHA-256.
+        let contents = "da39a3ee5e6b4b0d3255bfef95601890afd80709  tool-linux.tar.gz\n";
+
+        assert_eq!(expected_digest(contents, "tool-linux.tar.gz"), None);
+    }
+
+    #[test]
+    fn prefers_per_asset_checksums() {
+        let assets = [asset("checksums.txt"), asset("tool-linux.tar.gz"), asset("tool-linux.tar.gz.sha256")];
+
+        assert_eq!(checksum_asset(&assets, &assets[1]).map(|a| a.name.as_str()), Some("tool-linux.tar.gz.sha256"));
+    }
+
+    #[test]
+    fn finds_aggregate_checksums() {
+        let assets = [asset("tool_1.2.3_checksums.txt"), asset("tool-linux.tar.gz")];
+
+        assert_eq!(checksum_asset(&assets, &assets[1]).map(|a| a.name.as_str()), Some("tool_1.2.3_checksums.txt"));
+
+        let assets = [asset("SHA256SUMS"), asset("tool-linux.tar.gz")];
+
+        assert_eq!(checksum_asset(&assets, &assets[1]).map(|a| a.name.as_str()), Some("SHA256SUMS"));
+    }
+
+    #[test]
+    fn no_checksums() {
+        let assets = [asset("tool-linux.tar.gz"), asset("tool-linux.tar.gz.sig")];
+
+        assert!(checksum_asset(&assets, &assets[0]).is_none());
+    }
+}
@@ -63,3 +63,33 @@ pub fn parse(provided_version: &'_ str) -> Version {
         }
     }
 }
+
+/// Whether `s` looks like a version requirement (e.g. `^1.4`, `~0.9`, `<2`) rather than a single version.
+pub fn is_requirement(s: &'_ str) -> bool {
+    s.starts_with(['^', '~', '<', '>', '=', '*']) || s.contains(',')
+}
+
+/// Parse a version requirement, treating a bare version such as `1.2.3` as an exact pin.
+pub fn parse_requirement(requirement: &'_ str) -> Result<semver::VersionReq, semver::Error> {
+    let requirement = requirement.trim().trim_start_matches('v');
+
+    if semver::Version::parse(requirement).is_ok() {
+        semver::VersionReq::parse(&format!("={requirement}"))
+    } else {
+        semver::VersionReq::parse(requirement)
+    }
+}
+
+/// Whether an `installed` version falls outside of `requirement`.
+///
+/// Channels never drift, following them is what `update` is for.
+pub fn drifted(requirement: &'_ str, installed: &'_ str) -> bool {
+    if matches!(parse(requirement), Version::Latest | Version::Stable | Version::PreRelease | Version::Lts) {
+        return false;
+    }
+
+    match (parse_requirement(requirement), parse(installed)) {
+        (Ok(requirement), Version::SemVer(installed)) => !requirement.matches(&installed),
+        _ => false,
+    }
+}